@@ -0,0 +1,38 @@
+#version 330
+
+// adds a soft glow around bright pixels like the phosphor of a CRT
+
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+uniform vec2 resolution;
+
+out vec4 finalColor;
+
+const float radius = 3.0;
+const float threshold = 0.6;
+const float strength = 0.6;
+
+void main()
+{
+    vec4 source = texture(texture0, fragTexCoord);
+    vec2 texel = 1.0/resolution;
+
+    vec3 glow = vec3(0.0);
+    float weights = 0.0;
+    for (int x = -3; x <= 3; x++)
+    {
+        for (int y = -3; y <= 3; y++)
+        {
+            vec2 offset = vec2(x, y)*texel*radius;
+            vec3 neighbour = texture(texture0, fragTexCoord + offset).rgb;
+            float weight = 1.0 - length(vec2(x, y))/5.0;
+            glow += max(neighbour - threshold, 0.0)*max(weight, 0.0);
+            weights += max(weight, 0.0);
+        }
+    }
+
+    finalColor = vec4(source.rgb + glow/weights*strength/(1.0 - threshold), source.a)*colDiffuse*fragColor;
+}
@@ -0,0 +1,27 @@
+#version 330
+
+// splits the color channels towards the edges of the screen like a misconverged CRT
+
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+uniform vec2 resolution;
+
+out vec4 finalColor;
+
+const float amount = 2.5;
+
+void main()
+{
+    vec2 direction = fragTexCoord - 0.5;
+    // amount is roughly the split in pixels at the corners
+    vec2 offset = direction*length(direction)*amount*4.0/resolution;
+
+    float r = texture(texture0, fragTexCoord + offset).r;
+    vec2 ga = texture(texture0, fragTexCoord).ga;
+    float b = texture(texture0, fragTexCoord - offset).b;
+
+    finalColor = vec4(r, ga.x, b, ga.y)*colDiffuse*fragColor;
+}
@@ -0,0 +1,29 @@
+#version 330
+
+// bends the picture outwards like the glass of a curved CRT
+
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+out vec4 finalColor;
+
+const float curvature = 6.0;
+
+void main()
+{
+    vec2 uv = fragTexCoord*2.0 - 1.0;
+    vec2 offset = abs(uv.yx)/curvature;
+    uv = uv + uv*offset*offset;
+    uv = uv*0.5 + 0.5;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0)
+    {
+        finalColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    finalColor = texture(texture0, uv)*colDiffuse*fragColor;
+}
@@ -0,0 +1,20 @@
+#version 330
+
+// darkens every other line like the gaps between the beam lines of a CRT
+
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+uniform vec2 resolution;
+
+out vec4 finalColor;
+
+void main()
+{
+    vec4 color = texture(texture0, fragTexCoord);
+    float line = sin(fragTexCoord.y*resolution.y*3.14159);
+    color.rgb *= mix(0.75, 1.0, line*line);
+    finalColor = color*colDiffuse*fragColor;
+}
@@ -0,0 +1,22 @@
+#version 330
+
+// darkens the corners of the screen
+
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+out vec4 finalColor;
+
+const float radius = 0.75;
+const float softness = 0.45;
+
+void main()
+{
+    vec4 color = texture(texture0, fragTexCoord);
+    float distance = length(fragTexCoord - 0.5)*1.41421;
+    color.rgb *= smoothstep(radius, radius - softness, distance);
+    finalColor = color*colDiffuse*fragColor;
+}
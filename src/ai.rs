@@ -1,11 +1,15 @@
 use raylib::prelude::*;
-use crate::time::{DELTA_TIME, TIME};
+use crate::events::Side;
+use crate::game::Ball;
 
+#[derive(Clone)]
 pub struct AI {
     ball_position: Vector2,
     ball_velocity: Vector2,
     ball_speed: f32,
     ball_radius: f32,
+    // the side it defends, top and bottom paddles lie flat and move sideways
+    side: Side,
     pub position: Vector2,
     pub size: Vector2,
     pub speed: f32,
@@ -14,18 +18,21 @@ pub struct AI {
 }
 
 impl AI {
-    pub fn new(screen_width: f32, screen_height: f32) -> AI {
-        AI {
+    pub fn new(side: Side, screen_width: f32, screen_height: f32) -> AI {
+        let mut ai = AI {
             ball_position: Default::default(),
             ball_velocity: Default::default(),
             ball_speed: Default::default(),
             ball_radius: Default::default(),
-            position: Vector2::new(screen_width - 20.0, screen_height / 2.0 - 50.0),
-            size: Vector2::new(10.0, 100.0),
+            side,
+            position: Vector2::zero(),
+            size: if side.is_horizontal() { Vector2::new(100.0, 10.0) } else { Vector2::new(10.0, 100.0) },
             speed: 1000.0,
             screen_width,
             screen_height,
-        }
+        };
+        ai.update_screen_size(screen_width, screen_height);
+        ai
     }
 
     pub fn update_screen_size(&mut self, screen_width: f32, screen_height: f32) {
@@ -33,8 +40,12 @@ impl AI {
         self.screen_height = screen_height;
 
         // update position
-        self.position.x = screen_width - 20.0;
-        self.position.y = screen_height / 2.0 - 50.0;
+        self.position = match self.side {
+            Side::Left => Vector2::new(10.0, screen_height / 2.0 - 50.0),
+            Side::Right => Vector2::new(screen_width - 20.0, screen_height / 2.0 - 50.0),
+            Side::Top => Vector2::new(screen_width / 2.0 - 50.0, 10.0),
+            Side::Bottom => Vector2::new(screen_width / 2.0 - 50.0, screen_height - 20.0),
+        };
     }
 
     pub fn update_ball(&mut self, ball_position: Vector2, ball_velocity: Vector2, ball_speed: f32, ball_radius: f32) {
@@ -44,24 +55,74 @@ impl AI {
         self.ball_radius = ball_radius;
     }
 
-    pub fn update(&mut self) {
-        let delta_time = unsafe { DELTA_TIME };
-        // move Y towards ball
-        if self.position.y + self.size.y / 2.0 < self.ball_position.y {
-            self.position.y += self.speed * delta_time;
+    /// Chases the ball that reaches this side first, or the closest one when none is coming.
+    pub fn update_balls(&mut self, balls: &[Ball], ball_speed: f32, ball_radius: f32) {
+        let coming = balls.iter()
+            .filter_map(|ball| self.time_to_reach(ball).map(|time| (time, ball)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, ball)| ball);
+        let target = coming.or_else(|| balls.iter().min_by(|a, b| self.distance(a).total_cmp(&self.distance(b))));
+        if let Some(ball) = target {
+            self.update_ball(ball.position, ball.velocity, ball_speed, ball_radius);
         }
-        else if self.position.y + self.size.y / 2.0 > self.ball_position.y {
-            self.position.y -= self.speed * delta_time;
+    }
+
+    // distance of `ball` from the line this paddle moves along
+    fn distance(&self, ball: &Ball) -> f32 {
+        match self.side {
+            Side::Left | Side::Right => (ball.position.x - self.position.x).abs(),
+            Side::Top | Side::Bottom => (ball.position.y - self.position.y).abs(),
+        }
+    }
+
+    // how long until `ball` gets here, none when it moves away
+    fn time_to_reach(&self, ball: &Ball) -> Option<f32> {
+        let approach = match self.side {
+            Side::Left => -ball.velocity.x,
+            Side::Right => ball.velocity.x,
+            Side::Top => -ball.velocity.y,
+            Side::Bottom => ball.velocity.y,
+        };
+        if approach > 0.0 { Some(self.distance(ball) / approach) } else { None }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if self.side.is_horizontal() {
+            // move X towards ball
+            if self.position.x + self.size.x / 2.0 < self.ball_position.x {
+                self.position.x += self.speed * delta_time;
+            }
+            else if self.position.x + self.size.x / 2.0 > self.ball_position.x {
+                self.position.x -= self.speed * delta_time;
+            }
+        }
+        else {
+            // move Y towards ball
+            if self.position.y + self.size.y / 2.0 < self.ball_position.y {
+                self.position.y += self.speed * delta_time;
+            }
+            else if self.position.y + self.size.y / 2.0 > self.ball_position.y {
+                self.position.y -= self.speed * delta_time;
+            }
         }
 
         self.clamp_to_screen();
     }
 
     fn clamp_to_screen(&mut self) {
-        // clamp Y to screen
-        self.position.y = self.position.y.clamp(0.0,
-                                                self.screen_height - self.size.y);
-        self.position.x = self.screen_width - 20.0;
+        match self.side {
+            Side::Left | Side::Right => {
+                // clamp Y to screen
+                self.position.y = self.position.y.clamp(0.0,
+                                                        self.screen_height - self.size.y);
+                self.position.x = if self.side == Side::Left { 10.0 } else { self.screen_width - 20.0 };
+            }
+            Side::Top | Side::Bottom => {
+                self.position.x = self.position.x.clamp(0.0,
+                                                        self.screen_width - self.size.x);
+                self.position.y = if self.side == Side::Top { 10.0 } else { self.screen_height - 20.0 };
+            }
+        }
     }
 
-}
\ No newline at end of file
+}
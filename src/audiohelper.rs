@@ -2,13 +2,22 @@ use raylib::ffi::{InitAudioDevice, LoadSound, LoadSoundFromWave, LoadWaveFromMem
 use raylib::prelude::*;
 
 #[macro_export]
-macro_rules! load_from_memory {
+macro_rules! load_wave_from_memory {
     ($path:expr) => {
         {
             let sound_data = include_bytes!($path);
-            let wave = LoadWaveFromMemory(const_c!(".wav"),
-                                          sound_data.as_ptr() as *const _,
-                                          sound_data.len() as i32);
+            LoadWaveFromMemory(const_c!(".wav"),
+                               sound_data.as_ptr() as *const _,
+                               sound_data.len() as i32)
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! load_from_memory {
+    ($path:expr) => {
+        {
+            let wave = load_wave_from_memory!($path);
             LoadSoundFromWave(wave)
         }
     };
@@ -1,57 +1,309 @@
-use lazy_static::lazy_static;
-use raylib::ffi::{Sound, LoadSound, PlaySound, PlaySoundMulti, SetSoundPitch, SetSoundVolume, SetAudioStreamPitch, SetAudioStreamVolume, PlayAudioStream};
+use raylib::ffi::{Sound, Wave, LoadWave, LoadWaveFromMemory, LoadSoundFromWave, WaveCopy, WaveFormat, UnloadSound, UnloadWave, UpdateSound, PlaySound, StopSound, IsSoundPlaying, SetSoundPitch, SetSoundVolume};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Mutex;
-use crate::const_c;
+use crate::{const_c, load_wave_from_memory};
+use crate::events::{EventSubscriber, GameEvent, Side};
+use crate::floathelper::FloatHelper;
+use crate::settings::AudioSettings;
 
 pub struct SoundHandle {
     pub sound_type: SoundType,
     pub volume: f32,
     pub pitch: f32,
+    /// -1.0 is fully left, 0.0 centered, 1.0 fully right
+    pub pan: f32,
 }
 
-#[derive(Eq, Hash, PartialEq, Debug)]
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
 pub enum SoundType {
     Bounce,
+    WallBounce,
     PlayerScored,
     EnemyScored,
+    Countdown,
+}
+
+/// Where the sound effects come from.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundTheme {
+    /// the wav files in res/sounds
+    Samples,
+    /// square wave beeps generated by the synth at startup
+    Retro,
+}
+
+/// What to do when every voice of a sound is busy and a new instance is requested.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceStealPolicy {
+    /// restart the voice that was started first
+    Oldest,
+    /// restart the voice that was started with the lowest volume
+    Quietest,
+    /// drop the new instance
+    Reject,
 }
 
 pub(crate) static mut SOUND_STACK: Vec<SoundHandle> = Vec::new();
 
+// pitch added to the bounce sound per paddle hit in a rally
+const RALLY_PITCH_STEP: f32 = 0.02;
+const MAX_RALLY_PITCH_STEPS: u32 = 25;
+
+struct Voice {
+    sound: Sound,
+    volume: f32,
+    // pan currently baked into the voice buffer
+    pan: f32,
+    // value of SoundManager::play_count when this voice was last started
+    started: u64,
+}
+
 pub struct SoundManager {
-    sounds: HashMap<SoundType, Sound>,
+    waves: HashMap<SoundType, Wave>,
+    voices: HashMap<SoundType, Vec<Voice>>,
+    // interleaved stereo copy of each wave in the voice buffer format, used to bake the pan
+    stereo_samples: HashMap<SoundType, Vec<f32>>,
+    pan_buffer: Vec<f32>,
+    max_voices: usize,
+    steal_policy: VoiceStealPolicy,
+    positional: bool,
+    distance_attenuation: f32,
+    play_count: u64,
 }
 
 impl SoundManager {
-    pub fn new(map: Option<HashMap<SoundType, Sound>>) -> SoundManager {
+    pub fn new(map: Option<HashMap<SoundType, Wave>>, settings: &AudioSettings) -> SoundManager {
         SoundManager {
-            sounds: map.unwrap_or_else(|| { HashMap::new() }),
+            waves: map.unwrap_or_else(|| { HashMap::new() }),
+            voices: HashMap::new(),
+            stereo_samples: HashMap::new(),
+            pan_buffer: Vec::new(),
+            max_voices: settings.max_voices.max(1),
+            steal_policy: settings.voice_steal_policy,
+            positional: settings.positional,
+            distance_attenuation: settings.distance_attenuation.clamp(0.0, 1.0),
+            play_count: 0,
         }
     }
 
     pub fn load(&mut self, sound_type: SoundType, path: &str) {
-        let sound = unsafe { LoadSound(const_c!(path)) };
-        self.sounds.insert(sound_type, sound);
+        let wave = unsafe { LoadWave(const_c!(path)) };
+        // voices created from the previous wave would keep playing the old data
+        self.unload(sound_type);
+        self.waves.insert(sound_type, wave);
     }
 
-    pub fn play(&self, sound_type: &SoundType, volume: f32, pitch: f32) {
-        let sound = self.sounds.get(sound_type).expect(format!("Sound {:?} not found", sound_type).as_str());
+    // frees the wave of `sound_type` and every voice made from it
+    fn unload(&mut self, sound_type: SoundType) {
+        for voice in self.voices.remove(&sound_type).unwrap_or_default() {
+            unsafe { UnloadSound(voice.sound); }
+        }
+        self.stereo_samples.remove(&sound_type);
+        if let Some(wave) = self.waves.remove(&sound_type) {
+            unsafe { UnloadWave(wave); }
+        }
+    }
+
+    pub fn play(&mut self, sound_type: &SoundType, volume: f32, pitch: f32, pan: f32) {
+        let pan = if self.positional { pan.clamp(-1.0, 1.0) } else { 0.0 };
+        // sounds further away from the center of the arena get quieter
+        let volume = volume * (1.0 - self.distance_attenuation * pan.abs());
+
+        let wave = *self.waves.get(sound_type).expect(format!("Sound {:?} not found", sound_type).as_str());
+        let voices = self.voices.entry(*sound_type).or_insert_with(Vec::new);
+
+        let index = match voices.iter().position(|voice| unsafe { !IsSoundPlaying(voice.sound) }) {
+            Some(index) => index,
+            // every voice is busy, grow the pool while under the cap
+            None if voices.len() < self.max_voices => {
+                voices.push(Voice {
+                    sound: unsafe { LoadSoundFromWave(wave) },
+                    volume: 0.0,
+                    pan: 0.0,
+                    started: 0,
+                });
+                voices.len() - 1
+            }
+            None => match steal_voice(voices, self.steal_policy) {
+                Some(index) => index,
+                None => return,
+            },
+        };
+
+        self.play_count += 1;
+        let voice = &mut voices[index];
+        voice.volume = volume;
+        voice.started = self.play_count;
+
+        if voice.pan != pan {
+            let samples = self.stereo_samples.entry(*sound_type)
+                .or_insert_with(|| unsafe { stereo_samples(wave, voice.sound) });
+            bake_pan(voice, samples, pan, &mut self.pan_buffer);
+        }
+
+        // every voice owns its own buffer so volume and pitch don't leak between plays
         unsafe {
-            let c_sound = sound.clone();
-            SetAudioStreamPitch(c_sound.stream, pitch);
-            SetAudioStreamVolume(c_sound.stream, volume);
-            PlayAudioStream(c_sound.stream);
+            StopSound(voice.sound);
+            SetSoundVolume(voice.sound, volume);
+            SetSoundPitch(voice.sound, pitch);
+            PlaySound(voice.sound);
         };
     }
 }
 
+impl Drop for SoundManager {
+    // switching the sound theme replaces the whole manager
+    fn drop(&mut self) {
+        let sound_types: Vec<SoundType> = self.waves.keys().chain(self.voices.keys()).copied().collect();
+        for sound_type in sound_types {
+            self.unload(sound_type);
+        }
+    }
+}
+
+/// Converts `wave` to the format of the voice buffer of `sound`, empty if that buffer is not interleaved stereo.
+unsafe fn stereo_samples(wave: Wave, sound: Sound) -> Vec<f32> {
+    if sound.stream.channels != 2 || sound.stream.sampleSize != 32 {
+        return Vec::new();
+    }
+
+    let mut copy = WaveCopy(wave);
+    WaveFormat(&mut copy, sound.stream.sampleRate as i32, 32, 2);
+    let samples = std::slice::from_raw_parts(copy.data as *const f32, copy.sampleCount as usize).to_vec();
+    UnloadWave(copy);
+    samples
+}
+
+/// Rewrites the voice buffer with the stereo source scaled by a linear balance law.
+fn bake_pan(voice: &mut Voice, samples: &[f32], pan: f32, buffer: &mut Vec<f32>) {
+    if samples.is_empty() {
+        return;
+    }
+
+    // center keeps both channels at full volume
+    let left = (1.0 - pan).min(1.0);
+    let right = (1.0 + pan).min(1.0);
+
+    buffer.clear();
+    buffer.extend(samples.chunks_exact(2).flat_map(|frame| [frame[0] * left, frame[1] * right]));
+    // UpdateSound takes the number of frames and copies at most the buffer length
+    let frames = (buffer.len() / 2).min(voice.sound.sampleCount as usize / 2);
+    unsafe {
+        UpdateSound(voice.sound, buffer.as_ptr() as *const _, frames as i32);
+    }
+    voice.pan = pan;
+}
+
+fn steal_voice(voices: &[Voice], policy: VoiceStealPolicy) -> Option<usize> {
+    match policy {
+        VoiceStealPolicy::Oldest => voices.iter()
+            .enumerate()
+            .min_by_key(|(_, voice)| voice.started)
+            .map(|(index, _)| index),
+        VoiceStealPolicy::Quietest => voices.iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.volume.total_cmp(&b.volume))
+            .map(|(index, _)| index),
+        VoiceStealPolicy::Reject => None,
+    }
+}
+
+/// Picks the sound effect for gameplay events and pans it to where it happened.
+pub struct SfxEvents {
+    pub arena_width: f32,
+    // paddle hits since the last point, raises the pitch of the bounce sound
+    rally: u32,
+}
+
+impl SfxEvents {
+    pub fn new(arena_width: f32) -> SfxEvents {
+        SfxEvents {
+            arena_width,
+            rally: 0,
+        }
+    }
+
+    fn rally_pitch(&self) -> f32 {
+        1.0 + self.rally.min(MAX_RALLY_PITCH_STEPS) as f32 * RALLY_PITCH_STEP
+    }
+
+    // map a x position in the arena to a stereo pan between -1.0 (left) and 1.0 (right)
+    fn arena_pan(&self, x: f32) -> f32 {
+        (x.normalize(0.0, self.arena_width) * 2.0 - 1.0).clamp(-1.0, 1.0)
+    }
+}
+
+impl EventSubscriber for SfxEvents {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::PaddleHit { position, .. } => {
+                // higher the longer the rally goes
+                self.rally += 1;
+                play_sfx_panned(SoundType::Bounce, 0.5, self.rally_pitch(), self.arena_pan(position.x));
+            }
+            GameEvent::WallBounce { position, .. } => {
+                play_sfx_panned(SoundType::WallBounce, 0.5, 1.0, self.arena_pan(position.x));
+            }
+            GameEvent::Scored { side, conceded, .. } => {
+                self.rally = 0;
+                // good news unless the player lost the point, or let the goal in when nobody won it
+                let good = side.map_or(conceded != Side::Left, |side| side == Side::Left);
+                if good {
+                    play_sfx(SoundType::PlayerScored, 0.5, 1.0);
+                } else {
+                    play_sfx(SoundType::EnemyScored, 0.5, 1.0);
+                }
+            }
+            GameEvent::Countdown { number } => {
+                // the GO beep is an octave above the numbers
+                let pitch = if number == 0 { 2.0 } else { 1.0 };
+                play_sfx(SoundType::Countdown, 0.4, pitch);
+            }
+            GameEvent::Serve { .. } | GameEvent::MatchWon { .. } => {}
+        }
+    }
+}
+
+pub fn load_theme(theme: SoundTheme) -> HashMap<SoundType, Wave> {
+    match theme {
+        SoundTheme::Samples => sample_theme(),
+        SoundTheme::Retro => crate::synth::retro_theme(),
+    }
+}
+
+fn sample_theme() -> HashMap<SoundType, Wave> {
+    let mut sound_map = HashMap::new();
+    sound_map.insert(SoundType::Bounce, unsafe {
+        load_wave_from_memory!("..\\res\\sounds\\paddle_hit.wav")
+    });
+    sound_map.insert(SoundType::WallBounce, unsafe {
+        load_wave_from_memory!("..\\res\\sounds\\paddle_hit.wav")
+    });
+    sound_map.insert(SoundType::PlayerScored, unsafe {
+        load_wave_from_memory!("..\\res\\sounds\\win.wav")
+    });
+    sound_map.insert(SoundType::EnemyScored, unsafe {
+        load_wave_from_memory!("..\\res\\sounds\\lose.wav")
+    });
+    sound_map.insert(SoundType::Countdown, unsafe {
+        load_wave_from_memory!("..\\res\\sounds\\paddle_hit.wav")
+    });
+    sound_map
+}
+
 pub fn play_sfx(sound_type: SoundType, volume: f32, pitch: f32) {
+    play_sfx_panned(sound_type, volume, pitch, 0.0);
+}
+
+pub fn play_sfx_panned(sound_type: SoundType, volume: f32, pitch: f32, pan: f32) {
     unsafe {
         SOUND_STACK.push(SoundHandle {
             sound_type,
             volume,
             pitch,
+            pan,
         });
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,123 @@
+use raylib::prelude::*;
+use crate::events::{EventSubscriber, GameEvent};
+use crate::settings::EffectSettings;
+
+// shake at full trauma and intensity
+const MAX_SHAKE_OFFSET: f32 = 40.0;
+const MAX_SHAKE_ANGLE: f32 = 3.0;
+// trauma lost per second
+const TRAUMA_DECAY: f32 = 1.5;
+// zoom punch lost per second
+const ZOOM_DECAY: f32 = 0.6;
+// seconds the simulation freezes on a paddle hit at full intensity
+const MAX_HIT_STOP: f32 = 0.06;
+
+/// Screen shake, hit-stop and zoom punches, driven by gameplay events.
+pub struct CameraEffects {
+    pub settings: EffectSettings,
+    // 0.0 to 1.0, the shake grows with the square of the trauma
+    trauma: f32,
+    zoom_punch: f32,
+    hit_stop: f32,
+    time: f32,
+}
+
+impl CameraEffects {
+    pub fn new(settings: &EffectSettings) -> CameraEffects {
+        CameraEffects {
+            settings: settings.clone(),
+            trauma: 0.0,
+            zoom_punch: 0.0,
+            hit_stop: 0.0,
+            time: 0.0,
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    pub fn punch_zoom(&mut self, amount: f32) {
+        self.zoom_punch = self.zoom_punch.max(amount);
+    }
+
+    pub fn freeze(&mut self, seconds: f32) {
+        self.hit_stop = self.hit_stop.max(seconds);
+    }
+
+    /// True while a hit-stop holds the simulation.
+    pub fn is_frozen(&self) -> bool {
+        self.settings.enabled && self.hit_stop > 0.0
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        self.hit_stop = (self.hit_stop - delta_time).max(0.0);
+        self.trauma = (self.trauma - TRAUMA_DECAY * delta_time).max(0.0);
+        self.zoom_punch = (self.zoom_punch - ZOOM_DECAY * delta_time).max(0.0);
+    }
+
+    /// Camera centered on the screen with the current shake and zoom applied.
+    pub fn camera(&self, screen_width: i32, screen_height: i32) -> ffi::Camera2D {
+        let center = Vector2::new(screen_width as f32 / 2.0, screen_height as f32 / 2.0);
+        let mut camera = ffi::Camera2D {
+            offset: center.into(),
+            target: center.into(),
+            rotation: 0.0,
+            zoom: 1.0,
+        };
+        if !self.settings.enabled {
+            return camera;
+        }
+
+        // layered sines give a smooth shake that doesn't repeat too obviously
+        let shake = self.trauma * self.trauma * self.settings.shake;
+        let noise = |seed: f32| (self.time * 37.0 + seed).sin() * 0.6 + (self.time * 71.0 + seed * 2.3).sin() * 0.4;
+        camera.offset.x += MAX_SHAKE_OFFSET * shake * noise(0.0);
+        camera.offset.y += MAX_SHAKE_OFFSET * shake * noise(11.0);
+        camera.rotation = MAX_SHAKE_ANGLE * shake * noise(23.0);
+        camera.zoom += self.zoom_punch * self.settings.zoom;
+        camera
+    }
+
+    /// The shaking camera for an arena of `arena` size, scaled to fit and centered in `window`.
+    pub fn fitted_camera(&self, arena: (i32, i32), window: (i32, i32)) -> ffi::Camera2D {
+        let arena_size = Vector2::new(arena.0 as f32, arena.1 as f32);
+        let window_size = Vector2::new(window.0 as f32, window.1 as f32);
+        let scale = (window_size.x / arena_size.x).min(window_size.y / arena_size.y);
+
+        let mut camera = self.camera(arena.0, arena.1);
+        let shake = Vector2::new(camera.offset.x, camera.offset.y) - arena_size / 2.0;
+        camera.offset = (window_size / 2.0 + shake * scale).into();
+        camera.zoom *= scale;
+        camera
+    }
+}
+
+impl EventSubscriber for CameraEffects {
+    fn on_event(&mut self, event: &GameEvent) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        match *event {
+            GameEvent::PaddleHit { offset, speed, .. } => {
+                // hits near the paddle edges send the ball off steeply and hit harder
+                self.add_trauma(0.15 + 0.25 * offset.abs());
+                self.punch_zoom(0.015);
+                if speed >= self.settings.hit_stop_speed {
+                    self.freeze(MAX_HIT_STOP * self.settings.hit_stop);
+                }
+            }
+            GameEvent::Scored { .. } => {
+                self.add_trauma(0.6);
+                self.punch_zoom(0.06);
+            }
+            GameEvent::MatchWon { .. } => {
+                self.add_trauma(0.8);
+                self.punch_zoom(0.1);
+            }
+            GameEvent::WallBounce { .. } | GameEvent::Countdown { .. } | GameEvent::Serve { .. } => {}
+        }
+    }
+}
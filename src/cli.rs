@@ -0,0 +1,115 @@
+use crate::export::{ExportFormat, ExportOptions};
+use crate::netplay::LobbyAction;
+use crate::netsim::NetworkConditions;
+
+/// Command line options, everything is optional and the game starts in the menus without any.
+#[derive(Clone, Debug, Default)]
+pub struct CliOptions {
+    /// replay file to play back instead of showing the menus
+    pub replay: Option<String>,
+    /// seed of the first match, to reproduce a serve
+    pub seed: Option<u64>,
+    /// renders the replay to this path without opening a window
+    pub export: Option<ExportOptions>,
+    /// opens the lobby hosting, joining or watching a networked match
+    pub lobby: Option<LobbyAction>,
+    /// what the network simulator does to the packets this copy sends
+    pub conditions: NetworkConditions,
+    pub help: bool,
+}
+
+pub const USAGE: &str = "\
+usage: pong50th [options]
+  --replay <file>        play back a replay file
+  --seed <number>        seed of the first match
+  --host                 host a networked match on the port from the settings
+  --join <address>       join a networked match, e.g. 127.0.0.1:7750
+  --watch <address>      watch a networked match someone else hosts
+  --latency <ms>         hold back every packet sent, for testing netplay
+  --jitter <ms>          vary the latency by up to this much either way
+  --loss <percent>       lose this share of the packets sent
+  --duplicate <percent>  send this share of the packets twice
+  --reorder <percent>    let later packets overtake this share of the packets
+  --export <path>        render the replay to a .gif, a directory of png frames or - for raw frames on stdout
+  --format <name>        gif, png or raw, guessed from the export path by default
+  --size <WxH>           size of exported frames, 640x360 by default
+  --fps <number>         framerate of exported frames, 30 by default
+  --help                 show this help";
+
+impl CliOptions {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<CliOptions, String> {
+        let mut options = CliOptions::default();
+        let mut args = args.into_iter();
+        let mut export = None;
+        let mut format = None;
+        let mut size = (640, 360);
+        let mut fps = 30;
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+            match arg.as_str() {
+                "--replay" => options.replay = Some(value("--replay")?),
+                "--seed" => {
+                    let seed = value("--seed")?;
+                    options.seed = Some(seed.parse().map_err(|_| format!("invalid seed {}", seed))?);
+                }
+                "--host" => options.lobby = Some(LobbyAction::Host),
+                "--join" => options.lobby = Some(LobbyAction::Join(value("--join")?)),
+                "--watch" => options.lobby = Some(LobbyAction::Watch(value("--watch")?)),
+                "--latency" => {
+                    let text = value("--latency")?;
+                    options.conditions.latency = text.parse().map_err(|_| format!("invalid latency {}", text))?;
+                }
+                "--jitter" => {
+                    let text = value("--jitter")?;
+                    options.conditions.jitter = text.parse().map_err(|_| format!("invalid jitter {}", text))?;
+                }
+                "--loss" => options.conditions.loss = parse_percent(&value("--loss")?)?,
+                "--duplicate" => options.conditions.duplicate = parse_percent(&value("--duplicate")?)?,
+                "--reorder" => options.conditions.reorder = parse_percent(&value("--reorder")?)?,
+                "--export" => export = Some(value("--export")?),
+                "--format" => {
+                    let name = value("--format")?;
+                    format = Some(ExportFormat::parse(&name).ok_or_else(|| format!("unknown format {}", name))?);
+                }
+                "--size" => {
+                    let text = value("--size")?;
+                    size = parse_size(&text).ok_or_else(|| format!("invalid size {}", text))?;
+                }
+                "--fps" => {
+                    let text = value("--fps")?;
+                    fps = text.parse().ok().filter(|fps| *fps > 0).ok_or_else(|| format!("invalid fps {}", text))?;
+                }
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        if let Some(path) = export {
+            if options.replay.is_none() {
+                return Err("--export needs a --replay to render".to_string());
+            }
+            options.export = Some(ExportOptions::new(&path, format, size, fps));
+        }
+        Ok(options)
+    }
+}
+
+// 0 to 100, as a chance from 0.0 to 1.0
+fn parse_percent(text: &str) -> Result<f32, String> {
+    match text.parse::<f32>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent / 100.0),
+        _ => Err(format!("invalid percentage {}", text)),
+    }
+}
+
+// "1280x720"
+fn parse_size(text: &str) -> Option<(i32, i32)> {
+    let (width, height) = text.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    if size.0 > 0 && size.1 > 0 && size.0 <= u16::MAX as i32 && size.1 <= u16::MAX as i32 {
+        Some(size)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,66 @@
+use raylib::prelude::Color;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Parses `#rrggbb` or `#rrggbbaa`, the leading `#` is optional.
+pub fn parse_hex(text: &str) -> Option<Color> {
+    let hex = text.trim().trim_start_matches('#');
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some(Color::new(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
+
+pub fn to_hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}{:02x}", color.r, color.g, color.b, color.a)
+}
+
+pub fn lerp(from: Color, to: Color, t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    Color::new(mix(from.r, to.r), mix(from.g, to.g), mix(from.b, to.b), mix(from.a, to.a))
+}
+
+/// Samples a list of colors spread evenly over 0.0 to 1.0.
+pub fn ramp(colors: &[Color], t: f32) -> Color {
+    match colors.len() {
+        0 => Color::WHITE,
+        1 => colors[0],
+        len => {
+            let position = t.clamp(0.0, 1.0) * (len - 1) as f32;
+            let index = (position as usize).min(len - 2);
+            lerp(colors[index], colors[index + 1], position - index as f32)
+        }
+    }
+}
+
+/// serde adapter for colors written as hex strings, use with `#[serde(with = "colorhelper::hex")]`.
+pub mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_hex(&text).ok_or_else(|| serde::de::Error::custom(format!("invalid color {}", text)))
+    }
+}
+
+/// serde adapter for lists of hex colors.
+pub mod hex_list {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(colors: &[Color], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(colors.iter().map(|color| to_hex(*color)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Color>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|text| parse_hex(text).ok_or_else(|| serde::de::Error::custom(format!("invalid color {}", text))))
+            .collect()
+    }
+}
@@ -1,35 +1,64 @@
 use raylib::drawing::RaylibDrawHandle;
-use raylib::ffi::{DrawTexture, free, LoadImageFromMemory, LoadTextureFromImage, Texture, UnloadImage, UnloadTexture};
+use raylib::ffi::{DrawTexture, LoadImageFromMemory, LoadTextureFromImage, Texture, UnloadImage, UnloadTexture};
 use raylib::prelude::*;
+use serde::Deserialize;
 use crate::const_c;
-use crate::game::Game;
-use crate::time::{TIME};
+use crate::input::any_pressed;
+use crate::scene::{Context, Scene, SceneCommand, Transition};
+use crate::time::DELTA_TIME;
+use crate::tween::{Ease, Timeline};
+
+// pixels per second the roll moves up
+const SCROLL_SPEED: f32 = 80.0;
+const LOGO_GAP: f32 = 50.0;
+const SECTION_GAP: f32 = 40.0;
+// seconds the roll takes to fade in and out
+const FADE_TIME: f32 = 1.0;
+
+#[derive(Deserialize)]
+struct CreditEntry {
+    #[serde(default)]
+    role: Option<String>,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct CreditSection {
+    name: String,
+    entries: Vec<CreditEntry>,
+}
+
+#[derive(Deserialize)]
+struct CreditsData {
+    title: String,
+    description: String,
+    website: String,
+    license: String,
+    sections: Vec<CreditSection>,
+}
 
-const CREDITS_DURATION: f32 = 6.0;
+// one line of the roll, `gap` is the space above it
+struct Line {
+    text: String,
+    size: i32,
+    gap: f32,
+}
 
 pub(crate) struct Credits {
-    pub title: String,
-    pub author: String,
-    pub acknowledgements: Vec<String>,
-    pub version: String,
-    pub description: String,
-    pub website: String,
-    pub license: String,
+    lines: Vec<Line>,
     logo_texture: Texture,
+    // fades the roll in and out, its clock is the time since the credits were started
+    timeline: Timeline,
+    duration: f32,
 }
 
 impl Credits {
     pub fn new() -> Credits {
+        let data: CreditsData = toml::from_str(include_str!("../res/credits.toml"))
+            .expect("res/credits.toml is invalid");
+
         Credits {
-            title: String::from("Pong50th"),
-            author: String::from("PerfectlyFineCode"),
-            acknowledgements: vec![
-                String::from("[raylib-rs] Paul Clement (github.com/deltaphc)")
-            ],
-            version: String::from("0.1.0"),
-            description: String::from("A pong game celebrating 50th anniversary, made with raylib-rs"),
-            website: String::from("github.com/perfectlyfinecode/pong50th"),
-            license: String::from("MIT"),
+            lines: roll_lines(&data),
             logo_texture: unsafe {
                 let image_data = include_bytes!("..\\res\\images\\logo.png");
                 let img = LoadImageFromMemory(const_c!(".png"),
@@ -40,96 +69,109 @@ impl Credits {
                 UnloadImage(img);
                 tex
             },
+            timeline: Timeline::new(),
+            duration: 0.0,
         }
     }
 
-    pub fn update(&mut self, game: &mut Game) {
-        let time = unsafe { TIME };
-        if time > CREDITS_DURATION as f64 {
-            game.game_state = crate::game::GameState::Playing;
-            game.time_since_last_score = time;
-        }
+    fn height(&self) -> f32 {
+        self.logo_texture.height as f32 + LOGO_GAP + self.lines.iter().map(|line| line.gap + line.size as f32).sum::<f32>()
     }
 
     pub fn draw_credits(&mut self, d: &mut RaylibDrawHandle) {
-        let mut y = d.get_screen_height() as f32 / 2.0 - 50.0 - (self.acknowledgements.len() as f32 * 30.0) - 30.0*3.0 - 150.0;
+        // the roll starts just below the screen and moves up
+        let mut y = d.get_screen_height() as f32 - self.timeline.time() * SCROLL_SPEED;
         // center text x
         let x = d.get_screen_width() as f32 / 2.0;
 
-        let title = format!("Title: {}", &self.title);
-        let author = format!("By {}", &self.author);
-        let version = format!("Version: {}", &self.version);
-        let description = format!("Description: {}", &self.description);
-        let website = format!("Website: {}", &self.website);
-        let license = format!("License: {}", &self.license);
-
-        let title_width = measure_text(&title, 48);
-        let author_width = measure_text(&author, 48);
-        let version_width = measure_text(&version, 20);
-        let description_width = measure_text(&description, 20);
-        let website_width = measure_text(&website, 20);
-        let license_width = measure_text(&license, 20);
-
-        let title_x = (x - title_width as f32 / 2.0) as i32;
-        let author_x = (x - author_width as f32 / 2.0) as i32;
-        let version_x = (x - version_width as f32 / 2.0) as i32;
-        let description_x = (x - description_width as f32 / 2.0) as i32;
-        let website_x = (x - website_width as f32 / 2.0) as i32;
-        let license_x = (x - license_width as f32 / 2.0) as i32;
-
         // fade in and fade out credits
-        let time = unsafe { TIME };
-        let mut alpha = 1.0;
-        if time < CREDITS_DURATION as f64 / 2.0 {
-            alpha = time as f32;
-        }
-        else if time > CREDITS_DURATION as f64 / 4.0 {
-            alpha = (CREDITS_DURATION as f64 - time) as f32;
-        }
-
-        let fade_out_color = Color::WHITE.fade(alpha);
+        let fade_out_color = Color::WHITE.fade(self.timeline.value("alpha"));
 
         unsafe {
             let img = self.logo_texture;
             DrawTexture(img,
                         x as i32 - (img.width as f32 / 2.0) as i32,
                         y as i32,
-                        ffi::Color {
-                            r: fade_out_color.r,
-                            g: fade_out_color.g,
-                            b: fade_out_color.b,
-                            a: fade_out_color.a,
-                        });
-            y += img.height as f32 + 50.0 as f32;
+                        fade_out_color.into());
+            y += img.height as f32 + LOGO_GAP;
+        }
+
+        for line in &self.lines {
+            y += line.gap;
+            let width = measure_text(&line.text, line.size);
+            d.draw_text(&line.text, (x - width as f32 / 2.0) as i32, y as i32, line.size, fade_out_color);
+            y += line.size as f32;
+        }
+    }
+}
+
+impl Scene for Credits {
+    /// Rolls the credits again from the bottom of the screen.
+    fn enter(&mut self, _ctx: &mut Context) {
+        self.timeline.restart();
+    }
+
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand {
+        self.timeline.update(unsafe { DELTA_TIME });
+
+        // the roll is over once its last line left the top of the screen,
+        // rebuild the fade when that changes with the screen size
+        let duration = (ctx.game.screen_height as f32 + self.height()) / SCROLL_SPEED;
+        if duration != self.duration {
+            self.duration = duration;
+            let time = self.timeline.time();
+            self.timeline = fade_timeline(duration);
+            self.timeline.set_time(time);
         }
 
+        if self.timeline.is_finished() || any_pressed(rl) {
+            SceneCommand::Pop(Transition::Fade)
+        } else {
+            SceneCommand::None
+        }
+    }
 
-        // draw title
-        d.draw_text(title.as_str(), title_x, y as i32, 40, fade_out_color);
-        y += 50.0;
+    fn draw(&mut self, d: &mut RaylibDrawHandle, _ctx: &mut Context) {
+        self.draw_credits(d);
+    }
+}
 
-        // draw author
-        d.draw_text(author.as_str(), author_x, y as i32, 40, fade_out_color);
-        y += 50.0;
-        // draw acknowledgements
-        for author in &self.acknowledgements {
-            let author = format!("{}", author);
-            let author_width = measure_text(&author, 20);
-            let author_x = (x - author_width as f32 / 2.0) as i32;
+impl Drop for Credits {
+    fn drop(&mut self) {
+        unsafe { UnloadTexture(self.logo_texture); }
+    }
+}
 
-            d.draw_text(author.as_str(), author_x, y as i32, 20, fade_out_color);
-            y += 30.0;
+// fades in at the start and out over the last second of a roll lasting `duration`
+fn fade_timeline(duration: f32) -> Timeline {
+    let fade = FADE_TIME.min(duration / 2.0);
+    Timeline::new()
+        .then("alpha", 0.0, 1.0, fade, Ease::OutQuad)
+        .wait(duration - fade * 2.0)
+        .then("alpha", 1.0, 0.0, fade, Ease::InQuad)
+}
+
+fn roll_lines(data: &CreditsData) -> Vec<Line> {
+    let line = |text: String, size: i32, gap: f32| Line { text, size, gap };
+
+    let mut lines = vec![
+        line(data.title.clone(), 48, 0.0),
+        line(format!("Version {}", env!("CARGO_PKG_VERSION")), 20, 10.0),
+        line(data.description.clone(), 20, 10.0),
+    ];
+
+    for section in &data.sections {
+        lines.push(line(section.name.to_uppercase(), 30, SECTION_GAP));
+        for entry in &section.entries {
+            let text = match &entry.role {
+                Some(role) => format!("{}  -  {}", role, entry.name),
+                None => entry.name.clone(),
+            };
+            lines.push(line(text, 20, 10.0));
         }
-        // draw version
-        d.draw_text(version.as_str(),  version_x, y as i32, 20, fade_out_color);
-        y += 30.0;
-        // draw description
-        d.draw_text(description.as_str(), description_x, y as i32, 20, fade_out_color);
-        y += 30.0;
-        // draw website
-        d.draw_text(website.as_str(), website_x, y as i32, 20, fade_out_color);
-        y += 30.0;
-        // draw license
-        d.draw_text(license.as_str(), license_x, y as i32, 20, fade_out_color);
     }
-}
\ No newline at end of file
+
+    lines.push(line(data.website.clone(), 20, SECTION_GAP));
+    lines.push(line(format!("License: {}", data.license), 20, 10.0));
+    lines
+}
@@ -0,0 +1,114 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use crate::net::{Connection, Message, PROTOCOL_VERSION};
+use crate::settings::NetSettings;
+use crate::time::DELTA_TIME;
+
+// seconds between two beacons of a host
+const BEACON_INTERVAL: f32 = 1.0;
+// seconds a game stays listed after its last beacon
+const EXPIRY: f32 = 3.5;
+
+/// Announces a hosted game to everyone on the LAN listening on the discovery port.
+pub struct Announcer {
+    connection: Connection,
+    discovery_port: u16,
+    id: u32,
+    timer: f32,
+}
+
+impl Announcer {
+    pub fn new(settings: &NetSettings) -> io::Result<Announcer> {
+        let connection = Connection::bind(0)?;
+        connection.enable_broadcast()?;
+        Ok(Announcer {
+            connection,
+            discovery_port: settings.discovery_port,
+            id: rand::random(),
+            timer: 0.0,
+        })
+    }
+
+    /// Sends a beacon for the game on `settings.port` once a second.
+    pub fn update(&mut self, settings: &NetSettings, players: u8, capacity: u8) {
+        self.timer -= unsafe { DELTA_TIME };
+        if self.timer > 0.0 {
+            return;
+        }
+        self.timer = BEACON_INTERVAL;
+
+        let beacon = Message::Beacon {
+            version: PROTOCOL_VERSION,
+            id: self.id,
+            name: settings.name.clone(),
+            port: settings.port,
+            players,
+            capacity,
+        };
+        // broadcasts don't always come back to this machine, a second copy does
+        for address in [Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST] {
+            self.connection.send(&beacon, SocketAddr::from((address, self.discovery_port)));
+        }
+    }
+}
+
+/// A game some host on the LAN announced.
+#[derive(Clone, Debug)]
+pub struct DiscoveredGame {
+    pub name: String,
+    /// where to join, the host's address with its game port
+    pub address: SocketAddr,
+    pub version: u16,
+    pub players: u8,
+    pub capacity: u8,
+    id: u32,
+    // seconds since the last beacon
+    age: f32,
+}
+
+impl DiscoveredGame {
+    pub fn is_compatible(&self) -> bool {
+        self.version == PROTOCOL_VERSION
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.players >= self.capacity
+    }
+}
+
+/// Listens for beacons and keeps the list of games that are still announced.
+pub struct Browser {
+    connection: Connection,
+    pub games: Vec<DiscoveredGame>,
+}
+
+impl Browser {
+    pub fn new(settings: &NetSettings) -> io::Result<Browser> {
+        Ok(Browser {
+            connection: Connection::bind(settings.discovery_port)?,
+            games: Vec::new(),
+        })
+    }
+
+    pub fn update(&mut self) {
+        let delta_time = unsafe { DELTA_TIME };
+        for game in self.games.iter_mut() {
+            game.age += delta_time;
+        }
+
+        while let Some((message, from)) = self.connection.receive() {
+            if let Message::Beacon { version, id, name, port, players, capacity } = message {
+                let address = SocketAddr::new(from.ip(), port);
+                match self.games.iter_mut().find(|known| known.id == id) {
+                    // the address it was first heard from stays, a game on this machine is heard twice
+                    Some(known) => {
+                        known.players = players;
+                        known.age = 0.0;
+                    }
+                    None => self.games.push(DiscoveredGame { name, address, version, players, capacity, id, age: 0.0 }),
+                }
+            }
+        }
+        self.games.retain(|game| game.age < EXPIRY);
+    }
+}
@@ -0,0 +1,103 @@
+use raylib::prelude::Vector2;
+
+/// A side of the arena, the player defends the left side. Top and bottom only have
+/// paddles in four player matches.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Top, Side::Bottom];
+
+    /// Where the side's entry is in arrays with one entry per side.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The top and bottom paddles move left and right.
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, Side::Top | Side::Bottom)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Side::Left => "LEFT",
+            Side::Right => "RIGHT",
+            Side::Top => "TOP",
+            Side::Bottom => "BOTTOM",
+        }
+    }
+}
+
+/// Something that happened in the simulation, emitted by gameplay code and
+/// consumed by subscribers that decide what it sounds or looks like.
+#[derive(Clone, Copy, Debug)]
+pub enum GameEvent {
+    /// `offset` is where the ball touched the paddle, -1.0 top edge to 1.0 bottom edge, `speed` is how
+    /// fast the ball leaves it
+    PaddleHit { side: Side, position: Vector2, offset: f32, speed: f32 },
+    /// `side` is the wall the ball bounced off, `velocity` is the direction of the ball after the bounce
+    WallBounce { side: Side, position: Vector2, velocity: Vector2 },
+    /// `side` is the side that won the point, none when a four player goal only cost `conceded`
+    /// a life, `position` is where the ball left the arena
+    Scored { side: Option<Side>, conceded: Side, position: Vector2, score: [i32; 4] },
+    /// a number of the serve countdown appeared, 0 is the GO shown on the serve
+    Countdown { number: u32 },
+    Serve { position: Vector2, direction: Vector2 },
+    MatchWon { side: Side, score: [i32; 4] },
+}
+
+pub trait EventSubscriber {
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+pub(crate) static mut EVENT_QUEUE: Vec<GameEvent> = Vec::new();
+
+pub fn emit(event: GameEvent) {
+    unsafe {
+        EVENT_QUEUE.push(event);
+    }
+}
+
+/// Hands every queued event to each subscriber in order and empties the queue.
+pub fn dispatch(subscribers: &mut [&mut dyn EventSubscriber]) {
+    unsafe {
+        for event in EVENT_QUEUE.drain(..) {
+            for subscriber in subscribers.iter_mut() {
+                subscriber.on_event(&event);
+            }
+        }
+    }
+}
+
+/// How many events are queued.
+pub fn count() -> usize {
+    unsafe {
+        EVENT_QUEUE.len()
+    }
+}
+
+/// Takes the events queued after the first `start` ones out of the queue.
+pub fn take_from(start: usize) -> Vec<GameEvent> {
+    unsafe {
+        EVENT_QUEUE.split_off(start.min(EVENT_QUEUE.len()))
+    }
+}
+
+/// Drops queued events without handing them to anyone, e.g. after fast-forwarding a replay.
+pub fn discard() {
+    unsafe {
+        EVENT_QUEUE.clear();
+    }
+}
+
+/// Copies of the events queued so far, they stay queued for `dispatch`.
+pub fn queued() -> Vec<GameEvent> {
+    unsafe {
+        EVENT_QUEUE.clone()
+    }
+}
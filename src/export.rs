@@ -0,0 +1,206 @@
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Stdout, Write};
+use raylib::prelude::*;
+use crate::camera::CameraEffects;
+use crate::events;
+use crate::game::{Game, TICK};
+use crate::gif::GifEncoder;
+use crate::replay::Replay;
+use crate::time;
+
+// gif delays are counted in hundredths of a second, faster frames get slowed down by viewers
+const MAX_GIF_FPS: u32 = 50;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExportFormat {
+    /// a single animated gif
+    Gif,
+    /// numbered png files in a directory
+    Png,
+    /// raw RGBA frames on stdout, meant to be piped into ffmpeg
+    Raw,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<ExportFormat> {
+        match name {
+            "gif" => Some(ExportFormat::Gif),
+            "png" => Some(ExportFormat::Png),
+            "raw" => Some(ExportFormat::Raw),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from where the frames go, "-" is stdout and anything else that
+    /// isn't a .gif file is a directory for png frames.
+    pub fn from_path(path: &str) -> ExportFormat {
+        if path == "-" {
+            ExportFormat::Raw
+        } else if path.to_lowercase().ends_with(".gif") {
+            ExportFormat::Gif
+        } else {
+            ExportFormat::Png
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    pub path: String,
+    pub format: ExportFormat,
+    pub width: i32,
+    pub height: i32,
+    pub fps: u32,
+}
+
+impl ExportOptions {
+    pub fn new(path: &str, format: Option<ExportFormat>, size: (i32, i32), fps: u32) -> ExportOptions {
+        let format = format.unwrap_or_else(|| ExportFormat::from_path(path));
+        let fps = if format == ExportFormat::Gif { fps.min(MAX_GIF_FPS) } else { fps };
+        ExportOptions {
+            path: path.to_string(),
+            format,
+            width: size.0,
+            height: size.1,
+            fps: fps.max(1),
+        }
+    }
+}
+
+enum FrameSink {
+    Gif(GifEncoder<BufWriter<File>>),
+    Png { directory: String, frame: u32 },
+    Raw(BufWriter<Stdout>),
+}
+
+impl FrameSink {
+    fn open(options: &ExportOptions) -> io::Result<FrameSink> {
+        Ok(match options.format {
+            ExportFormat::Gif => {
+                let file = BufWriter::new(File::create(&options.path)?);
+                FrameSink::Gif(GifEncoder::new(file, options.width as u16, options.height as u16, options.fps)?)
+            }
+            ExportFormat::Png => {
+                fs::create_dir_all(&options.path)?;
+                FrameSink::Png { directory: options.path.clone(), frame: 0 }
+            }
+            ExportFormat::Raw => FrameSink::Raw(BufWriter::new(io::stdout())),
+        })
+    }
+
+    // `image` is RGBA with 8 bits per channel
+    fn write(&mut self, image: ffi::Image) -> io::Result<()> {
+        let pixels = unsafe {
+            std::slice::from_raw_parts(image.data as *const u8, (image.width * image.height * 4) as usize)
+        };
+
+        match self {
+            FrameSink::Gif(encoder) => encoder.write_frame(pixels),
+            FrameSink::Png { directory, frame } => {
+                *frame += 1;
+                let path = format!("{}/frame_{:05}.png", directory, frame);
+                let c_path = CString::new(path.clone()).unwrap();
+                if unsafe { ffi::ExportImage(image, c_path.as_ptr()) } {
+                    Ok(())
+                } else {
+                    Err(io::Error::new(io::ErrorKind::Other, format!("failed to write {}", path)))
+                }
+            }
+            FrameSink::Raw(stdout) => stdout.write_all(pixels),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            FrameSink::Gif(encoder) => encoder.finish().map(|_| ()),
+            FrameSink::Png { .. } => Ok(()),
+            FrameSink::Raw(mut stdout) => stdout.flush(),
+        }
+    }
+}
+
+/// Renders `replay` off-screen frame by frame at the size and framerate of `options`,
+/// the simulation runs exactly as it did in the match. Returns the number of frames written.
+pub fn export(rl: &mut RaylibHandle,
+              thread: &RaylibThread,
+              game: &mut Game,
+              camera_effects: &mut CameraEffects,
+              replay: &Replay,
+              options: &ExportOptions) -> io::Result<u32> {
+    let mut sink = FrameSink::open(options)?;
+    let frame_time = 1.0 / options.fps as f32;
+    let target = unsafe { ffi::LoadRenderTexture(options.width, options.height) };
+
+    game.fixed_arena = true;
+    game.screen_width = replay.arena.0;
+    game.screen_height = replay.arena.1;
+    game.serve = replay.settings.serve.clone();
+    game.mode = replay.mode;
+    game.four_player = replay.settings.four_player.clone();
+    game.multi_ball = replay.settings.multi_ball.clone();
+    game.start_match(replay.seed);
+    // particles are drawn too, their randomness has to repeat as well
+    game.particles.seed(replay.seed);
+
+    let mut tick = 0;
+    let mut frames = 0;
+    let mut accumulator = 0.0;
+    let mut result = Ok(());
+
+    while tick < replay.inputs.len() && result.is_ok() {
+        // the clock only moves with the frames so every export of a replay looks the same
+        unsafe {
+            time::TIME = frames as f64 * frame_time as f64;
+            time::DELTA_TIME = frame_time;
+        }
+
+        let frozen = camera_effects.is_frozen();
+        if !frozen {
+            accumulator += frame_time;
+            while accumulator >= TICK && tick < replay.inputs.len() {
+                accumulator -= TICK;
+                game.step(replay.inputs[tick]);
+                tick += 1;
+            }
+        }
+
+        events::dispatch(&mut [&mut game.particles, &mut *camera_effects]);
+        if !frozen {
+            game.particles.update(frame_time);
+        }
+        camera_effects.update(frame_time);
+
+        // the same drawing as a match, into the render target instead of the window
+        let camera = camera_effects.fitted_camera(replay.arena, (options.width, options.height));
+        {
+            let mut d = rl.begin_drawing(thread);
+            unsafe {
+                ffi::BeginTextureMode(target);
+                ffi::ClearBackground(game.theme.colors.background.into());
+                ffi::BeginMode2D(camera);
+            }
+            game.draw_background(&mut d);
+            game.draw(&mut d);
+            unsafe {
+                ffi::EndMode2D();
+                ffi::EndTextureMode();
+            }
+        }
+
+        // render textures are stored upside down
+        unsafe {
+            let mut image = ffi::GetTextureData(target.texture);
+            ffi::ImageFlipVertical(&mut image);
+            ffi::ImageFormat(&mut image, ffi::PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8 as i32);
+            result = sink.write(image);
+            ffi::UnloadImage(image);
+        }
+        frames += 1;
+    }
+
+    unsafe { ffi::UnloadRenderTexture(target); }
+    result?;
+    sink.finish()?;
+    Ok(frames)
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::ptr::null_mut;
+use raylib::ffi::{DrawTextEx, FileExists, Font, GetFontDefault, LoadFont, LoadFontEx, MeasureTextEx, UnloadFont};
+use raylib::prelude::*;
+use crate::const_c;
+
+// size ttf and otf fonts are rasterized at, text is scaled from this
+const TTF_BASE_SIZE: i32 = 128;
+// spacing between characters relative to the font size, matches raylib's default font
+const SPACING: f32 = 0.1;
+
+/// Loads fonts once and hands out the same font for every later request of a path.
+pub struct FontManager {
+    fonts: HashMap<String, Font>,
+}
+
+impl FontManager {
+    pub fn new() -> FontManager {
+        FontManager {
+            fonts: HashMap::new(),
+        }
+    }
+
+    /// Loads a ttf/otf font or a bitmap font (fnt or png image font).
+    pub fn load(&mut self, path: &str) -> Option<Font> {
+        if let Some(font) = self.fonts.get(path) {
+            return Some(*font);
+        }
+
+        let font = unsafe {
+            if !FileExists(const_c!(path)) {
+                eprintln!("font {} not found", path);
+                return None;
+            }
+
+            let extension = Path::new(path).extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            match extension.as_str() {
+                "ttf" | "otf" => LoadFontEx(const_c!(path), TTF_BASE_SIZE, null_mut(), 0),
+                _ => LoadFont(const_c!(path)),
+            }
+        };
+        self.fonts.insert(path.to_string(), font);
+        Some(font)
+    }
+}
+
+impl Drop for FontManager {
+    fn drop(&mut self) {
+        for font in self.fonts.values() {
+            unsafe { UnloadFont(*font); }
+        }
+    }
+}
+
+fn font_or_default(font: Option<Font>) -> Font {
+    font.unwrap_or_else(|| unsafe { GetFontDefault() })
+}
+
+/// Size of `text` in pixels, `None` measures with raylib's default font.
+pub fn measure(font: Option<Font>, text: &str, size: f32) -> Vector2 {
+    unsafe { MeasureTextEx(font_or_default(font), const_c!(text), size, size * SPACING).into() }
+}
+
+pub fn draw(font: Option<Font>, text: &str, position: Vector2, size: f32, color: Color) {
+    unsafe {
+        DrawTextEx(font_or_default(font), const_c!(text), position.into(), size, size * SPACING, color.into());
+    }
+}
+
+/// Draws `text` with its measured center at `center`.
+pub fn draw_centered(font: Option<Font>, text: &str, center: Vector2, size: f32, color: Color) {
+    let bounds = measure(font, text, size);
+    draw(font, text, center - bounds / 2.0, size, color);
+}
@@ -1,55 +1,252 @@
+use std::collections::VecDeque;
 use std::ffi::c_char;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use raylib::ffi::MeasureTextEx;
 use raylib::prelude::*;
 use crate::ai::AI;
-use crate::audiosystem::{play_sfx, SoundType};
+use crate::events::{emit, GameEvent, Side};
 use crate::debug::draw_line;
 use crate::floathelper::FloatHelper;
+use crate::fontmanager;
+use crate::input::{poll_gamepad_input, poll_player_input, PaddleInput};
+use crate::particles::ParticleSystem;
+use crate::replay::Recorder;
+use crate::settings::{FourPlayerScoring, FourPlayerSettings, InstantReplaySettings, MultiBallSettings, MultiBallSpawn, ServeSettings, Settings, TrailSettings};
 pub use crate::gamestate::GameState;
+use crate::scene::{Context, Scene, SceneCommand, Transition};
 use crate::time;
+use crate::theme::Theme;
+use crate::trail::draw_trail;
+use crate::tween::{Ease, Timeline};
 use crate::vectorhelper::Vector2Ext;
 
+/// Simulation steps per second.
+pub const TICK_RATE: f32 = 120.0;
+pub const TICK: f32 = 1.0 / TICK_RATE;
+// ticks simulated at most per frame before the simulation slows down
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+// seconds the GO stays on screen after the serve
+const GO_TIME: f32 = 0.5;
+
+// points needed to win a match
+pub const WINNING_SCORE: i32 = 11;
+
+// the paddles are 100 pixels long, an arena needs room for them and the ball on every side
+const MIN_ARENA_SIZE: i32 = 200;
+
+/// Whether a match can be simulated in `arena`, for sizes that come from another machine or a file.
+pub fn is_valid_arena(arena: (i32, i32)) -> bool {
+    let sizes = MIN_ARENA_SIZE..=u16::MAX as i32;
+    sizes.contains(&arena.0) && sizes.contains(&arena.1)
+}
+
+/// Which sides of the arena have a paddle.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameMode {
+    /// left against right, the ball bounces off the top and bottom walls
+    Classic,
+    /// a paddle on every side, the AI plays the ones nobody else does
+    FourPlayer,
+    /// left against right, more balls join the rally as it goes on
+    MultiBall,
+}
+
+/// A ball in play, it collides and scores on its own and leaves its own trail.
+#[derive(Clone, Debug)]
+pub struct Ball {
+    pub position: Vector2,
+    pub velocity: Vector2,
+    // position of the previous ticks, newest first
+    history: VecDeque<Vector2>,
+}
+
+impl Ball {
+    fn new(position: Vector2, velocity: Vector2) -> Ball {
+        Ball { position, velocity, history: VecDeque::new() }
+    }
+}
+
+/// Where everything was on one tick, enough to draw the arena again.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub ball_positions: Vec<Vector2>,
+    pub player_position: Vector2,
+    pub enemy_position: Vector2,
+    pub top_position: Vector2,
+    pub bottom_position: Vector2,
+}
+
+/// What a networked client needs from the host to draw the match.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MatchState {
+    pub ball_position: Vector2,
+    pub ball_velocity: Vector2,
+    pub left_paddle: f32,
+    pub right_paddle: f32,
+    pub score: (i32, i32),
+    pub serve_timer: f32,
+    pub go_timer: f32,
+    pub paused: bool,
+    pub match_over: bool,
+}
+
+/// Everything the simulation depends on, saved every tick so rollback netcode can go back
+/// and simulate again with inputs that arrived late.
+#[derive(Clone)]
+pub struct SimulationState {
+    balls: Vec<Ball>,
+    ball_speed: f32,
+    spawn_timer: f32,
+    spawn_hits: u32,
+    player_position: Vector2,
+    enemy_ai: AI,
+    top_ai: AI,
+    bottom_ai: AI,
+    score: [i32; 4],
+    last_hit: Option<Side>,
+    serve_timer: f32,
+    go_timer: f32,
+    paused: bool,
+    match_over: bool,
+    rng: StdRng,
+}
+
+impl SimulationState {
+    /// FNV-1a over the exact bits of the state, equal on two machines only if they simulated the same.
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut add = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        for ball in self.balls.iter() {
+            for value in [ball.position.x, ball.position.y, ball.velocity.x, ball.velocity.y] {
+                add(&value.to_bits().to_le_bytes());
+            }
+        }
+        for value in [self.ball_speed, self.spawn_timer, self.player_position.y, self.enemy_ai.position.y, self.top_ai.position.x,
+            self.bottom_ai.position.x, self.serve_timer, self.go_timer] {
+            add(&value.to_bits().to_le_bytes());
+        }
+        for score in self.score {
+            add(&score.to_le_bytes());
+        }
+        add(&self.spawn_hits.to_le_bytes());
+        add(&[self.paused as u8, self.match_over as u8, self.last_hit.map_or(4, |side| side.index() as u8)]);
+        // the next random number stands in for the generator state
+        add(&self.rng.clone().gen::<u64>().to_le_bytes());
+        hash
+    }
+}
+
 pub struct Game {
-    ball_position: Vector2,
-    ball_velocity: Vector2,
+    // the first ball is the one served, a multi-ball rally adds the others
+    balls: Vec<Ball>,
     ball_speed: f32,
     ball_radius: f32,
-    ball_position_history: [Vector2; 10],
     player_position: Vector2,
     player_size: Vector2,
     player_speed: f32,
     enemy_ai: AI,
-    score: (i32, i32),
-    pub(crate) time_since_last_score: f64,
+    // the paddles of a four player match
+    top_ai: AI,
+    bottom_ai: AI,
+    // points of each side by `Side::index`, lives left in a four player match played for lives
+    score: [i32; 4],
+    // the paddle that touched the ball last in this rally
+    last_hit: Option<Side>,
+    pub mode: GameMode,
+    pub four_player: FourPlayerSettings,
+    pub multi_ball: MultiBallSettings,
+    // seconds of the rally and paddle hits since the last ball joined a multi-ball rally
+    spawn_timer: f32,
+    spawn_hits: u32,
+    // seconds left until the ball is served, counted in simulation ticks
+    serve_timer: f32,
+    go_timer: f32,
+    number_timeline: Timeline,
+    go_timeline: Timeline,
     pub(crate) paused: bool,
     pub screen_width: i32,
     pub screen_height: i32,
-    pub game_state: GameState,
+    /// the side this machine plays, the right one for clients of a networked match and none
+    /// for spectators
+    pub local_side: Option<Side>,
+    // set when a side reached WINNING_SCORE, the simulation stops until the next match
+    match_over: bool,
+    // every random choice of the simulation comes from here so a seed replays a match exactly
+    rng: StdRng,
+    seed: u64,
+    /// keeps the arena size when the window is resized, replays need the recorded size
+    pub fixed_arena: bool,
+    pub trail: TrailSettings,
+    pub serve: ServeSettings,
+    pub instant_replay: InstantReplaySettings,
+    // snapshots of the current rally, oldest first, at most instant_replay.seconds long
+    rally: VecDeque<Snapshot>,
+    // set on a goal until taken by the playing scene
+    goal_scored: bool,
+    pub particles: ParticleSystem,
+    pub theme: Theme,
+    tick_accumulator: f32,
 }
 
 impl Game {
-    pub fn new(rl: &mut RaylibHandle) -> Self {
+    pub fn new(rl: &mut RaylibHandle, settings: &Settings, theme: Theme) -> Self {
         Self {
-            ball_position: /* center screen */ Vector2::new(rl.get_screen_width() as f32 / 2.0, rl.get_screen_height() as f32 / 2.0),
-            ball_velocity: Vector2::UP + Vector2::LEFT * 0.5,
+            balls: vec![Ball::new(/* center screen */ Vector2::new(rl.get_screen_width() as f32 / 2.0, rl.get_screen_height() as f32 / 2.0),
+                                  Vector2::UP + Vector2::LEFT * 0.5)],
             ball_speed: 3000.0,
             ball_radius: 10.0,
             player_position: Vector2::new(10.0, rl.get_screen_height() as f32 / 2.0 - 50.0),
             player_size: Vector2::new(10.0, 100.0),
             player_speed: 2000.0,
-            enemy_ai: AI::new(rl.get_screen_width() as f32, rl.get_screen_height() as f32),
-            score: (0, 0),
+            enemy_ai: AI::new(Side::Right, rl.get_screen_width() as f32, rl.get_screen_height() as f32),
+            top_ai: AI::new(Side::Top, rl.get_screen_width() as f32, rl.get_screen_height() as f32),
+            bottom_ai: AI::new(Side::Bottom, rl.get_screen_width() as f32, rl.get_screen_height() as f32),
+            score: [0; 4],
+            last_hit: None,
+            mode: GameMode::Classic,
+            four_player: settings.four_player.clone(),
+            multi_ball: settings.multi_ball.clone(),
+            spawn_timer: 0.0,
+            spawn_hits: 0,
             screen_width: rl.get_screen_width(),
             screen_height: rl.get_screen_height(),
-            time_since_last_score: 0.0,
+            serve_timer: settings.serve.countdown as f32,
+            go_timer: 0.0,
+            number_timeline: Timeline::new()
+                .then("scale", 1.5, 1.0, 1.0, Ease::OutCubic)
+                .at(0.0, "alpha", 0.0, 1.0, 0.1, Ease::Linear)
+                .at(0.75, "alpha", 1.0, 0.0, 0.25, Ease::InQuad),
+            go_timeline: Timeline::new()
+                .then("scale", 1.0, 2.0, GO_TIME, Ease::OutQuad)
+                .with("alpha", 1.0, 0.0, GO_TIME, Ease::Linear),
             paused: true,
-            game_state: GameState::Credits,
-            ball_position_history: [Vector2::new(rl.get_screen_width() as f32 / 2.0, rl.get_screen_height() as f32 / 2.0); 10],
+            local_side: Some(Side::Left),
+            match_over: false,
+            rng: StdRng::seed_from_u64(0),
+            seed: 0,
+            fixed_arena: false,
+            trail: settings.trail.clone(),
+            serve: settings.serve.clone(),
+            instant_replay: settings.instant_replay.clone(),
+            rally: VecDeque::new(),
+            goal_scored: false,
+            particles: ParticleSystem::new(&settings.particles),
+            theme,
+            tick_accumulator: 0.0,
         }
     }
 
     pub fn set_screen_size(&mut self, width: i32, height: i32) {
+        if self.fixed_arena {
+            return;
+        }
         // branchless check for screen size change
         if self.screen_width != width || self.screen_height != height {
             self.screen_width = width;
@@ -63,278 +260,690 @@ impl Game {
                                             self.screen_height as f32 / 2.0 - 50.0);
         self.enemy_ai.update_screen_size(self.screen_width as f32,
                                          self.screen_height as f32);
-        self.ball_position = Vector2::new(self.screen_width as f32 / 2.0,
-                                          self.screen_height as f32 / 2.0);
+        self.top_ai.update_screen_size(self.screen_width as f32, self.screen_height as f32);
+        self.bottom_ai.update_screen_size(self.screen_width as f32, self.screen_height as f32);
+        // the extra balls of a multi-ball rally are gone, the served one waits in the center
+        self.balls.truncate(1);
+        self.balls[0].position = Vector2::new(self.screen_width as f32 / 2.0,
+                                              self.screen_height as f32 / 2.0);
+        self.balls[0].history.clear();
+    }
+
+    /// Holds the ball in the center and starts the countdown to the next serve.
+    fn start_serve(&mut self) {
+        self.serve_timer = self.serve.countdown as f32;
+        self.go_timer = 0.0;
+        self.paused = true;
+        self.last_hit = None;
+        self.spawn_timer = 0.0;
+        self.spawn_hits = 0;
+        if self.serve.countdown > 0 {
+            emit(GameEvent::Countdown { number: self.serve.countdown });
+        }
     }
 
-    fn countdown(&mut self, rl: &mut RaylibHandle) {
-        // count down from 3
-        self.paused = rl.get_time() - self.time_since_last_score < 3.0;
+    fn countdown(&mut self) {
+        // a new number appears every whole second
+        let number = self.serve_timer.ceil();
+        self.serve_timer -= TICK;
+
+        if self.serve_timer > 0.0 {
+            if self.serve_timer.ceil() != number {
+                emit(GameEvent::Countdown { number: self.serve_timer.ceil() as u32 });
+            }
+            return;
+        }
+
+        self.paused = false;
+        if self.serve.countdown > 0 {
+            self.go_timer = GO_TIME;
+            emit(GameEvent::Countdown { number: 0 });
+        }
+        self.rally.clear();
+        emit(GameEvent::Serve {
+            position: self.balls[0].position,
+            direction: self.balls[0].velocity,
+        });
     }
 
-    pub fn update(&mut self, rl: &mut RaylibHandle) {
-        self.countdown(rl);
+    /// Starts a new match from 0 - 0 after the countdown, with a fresh seed.
+    pub fn reset_match(&mut self) {
+        self.start_match(rand::random());
+    }
 
-        let delta_time = unsafe { time::DELTA_TIME };
+    /// Starts a new match whose simulation only depends on `seed` and the inputs of each tick.
+    pub fn start_match(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        self.score = match self.mode {
+            GameMode::FourPlayer if self.four_player.scoring == FourPlayerScoring::Lives => [self.four_player.lives.max(1); 4],
+            _ => [0; 4],
+        };
+        self.reposition_entities();
+        self.tick_accumulator = 0.0;
+        self.rally.clear();
+        self.goal_scored = false;
+        self.balls[0].velocity = get_random_direction(&mut self.rng);
+        self.start_serve();
+        self.match_over = false;
+    }
 
-        self.update_player_movement(rl, delta_time);
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Points of each side by `Side::index`, lives left in a four player match played for lives.
+    pub fn score(&self) -> [i32; 4] {
+        self.score
+    }
+
+    pub fn is_match_over(&self) -> bool {
+        self.match_over
+    }
+
+    /// Runs the simulation for the time of this frame, returns how many ticks `input` was used for.
+    pub fn update(&mut self, input: PaddleInput) -> u32 {
+        self.run_ticks(|game| game.step(input))
+    }
+
+    /// Like `update` with a human on the right paddle, `right` gives their input for each tick.
+    pub fn update_versus(&mut self, input: PaddleInput, mut right: impl FnMut() -> PaddleInput) -> u32 {
+        self.run_ticks(|game| game.step_versus(input, Some(right())))
+    }
+
+    /// Like `update` with the inputs of every side by `Side::index`, the AI plays the sides without one.
+    pub fn update_players(&mut self, inputs: [Option<PaddleInput>; 4]) -> u32 {
+        self.run_ticks(|game| game.step_players(inputs))
+    }
+
+    fn run_ticks(&mut self, mut step: impl FnMut(&mut Game)) -> u32 {
+        let mut ticks = 0;
+        // run the simulation in fixed steps, dropping time the game can't catch up on
+        self.tick_accumulator += unsafe { time::DELTA_TIME };
+        self.tick_accumulator = self.tick_accumulator.min(MAX_TICKS_PER_FRAME as f32 * TICK);
+        while self.tick_accumulator >= TICK && !self.match_over {
+            self.tick_accumulator -= TICK;
+            step(self);
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// Advances the simulation by one tick.
+    pub(crate) fn step(&mut self, input: PaddleInput) {
+        self.step_versus(input, None);
+    }
+
+    /// Advances the simulation by one tick, the right paddle follows `right` instead of the AI when set.
+    pub(crate) fn step_versus(&mut self, input: PaddleInput, right: Option<PaddleInput>) {
+        self.step_players([Some(input), right, None, None]);
+    }
+
+    /// Advances the simulation by one tick, every paddle with an input follows it instead of the AI.
+    pub(crate) fn step_players(&mut self, inputs: [Option<PaddleInput>; 4]) {
+        self.update_player_movement(inputs[Side::Left.index()].unwrap_or_default(), TICK);
+        if let Some(right) = inputs[Side::Right.index()] {
+            self.enemy_ai.position.y = self.step_paddle(self.enemy_ai.position.y, right);
+        }
+        let four_player = self.mode == GameMode::FourPlayer;
+        if four_player {
+            if let Some(top) = inputs[Side::Top.index()] {
+                self.top_ai.position.x = self.step_paddle_across(self.top_ai.position.x, top);
+            }
+            if let Some(bottom) = inputs[Side::Bottom.index()] {
+                self.bottom_ai.position.x = self.step_paddle_across(self.bottom_ai.position.x, bottom);
+            }
+        }
+
+        // add position to history and remove oldest entry
+        for ball in self.balls.iter_mut() {
+            ball.history.push_front(ball.position);
+            ball.history.truncate(self.trail.length);
+        }
 
         if self.paused {
+            self.countdown();
             return;
         }
+        self.go_timer = (self.go_timer - TICK).max(0.0);
 
-        self.enemy_ai.update();
+        if inputs[Side::Right.index()].is_none() {
+            self.enemy_ai.update(TICK);
+        }
+        if four_player {
+            if inputs[Side::Top.index()].is_none() {
+                self.top_ai.update(TICK);
+            }
+            if inputs[Side::Bottom.index()].is_none() {
+                self.bottom_ai.update(TICK);
+            }
+        }
 
         // update ball position and velocity
-        self.ball_position += self.ball_velocity * (self.ball_speed / (self.screen_width as f32 / self.screen_height as f32)) * delta_time;
-        self.enemy_ai.update_ball(self.ball_position, self.ball_velocity, self.ball_speed, self.ball_radius);
+        let speed = self.ball_speed / (self.screen_width as f32 / self.screen_height as f32);
+        for ball in self.balls.iter_mut() {
+            ball.position += ball.velocity * speed * TICK;
+        }
+        self.enemy_ai.update_balls(&self.balls, self.ball_speed, self.ball_radius);
+        if four_player {
+            self.top_ai.update_balls(&self.balls, self.ball_speed, self.ball_radius);
+            self.bottom_ai.update_balls(&self.balls, self.ball_speed, self.ball_radius);
+        }
+        // taken before the collision so the goal tick still shows the ball leaving the arena
+        self.record_snapshot();
         self.check_collision();
+        if self.mode == GameMode::MultiBall && !self.paused {
+            self.spawn_ball();
+        }
     }
 
-    fn update_player_movement(&mut self, rl: &mut RaylibHandle, delta_time: f32) {
-        let position = &mut self.player_position;
-        // add position to history and remove oldest entry
-        self.ball_position_history.rotate_right(1);
-        self.ball_position_history[0] = self.ball_position;
+    // adds a ball in the center once the rally went on long enough
+    fn spawn_ball(&mut self) {
+        self.spawn_timer += TICK;
+        let due = match self.multi_ball.spawn {
+            MultiBallSpawn::Interval => self.spawn_timer >= self.multi_ball.interval,
+            MultiBallSpawn::Hits => self.spawn_hits >= self.multi_ball.hits.max(1),
+        };
+        if !due || self.balls.len() >= self.multi_ball.max_balls {
+            return;
+        }
+        self.spawn_timer = 0.0;
+        self.spawn_hits = 0;
 
-        // check W and S keys
-        if rl.is_key_down(KeyboardKey::KEY_W) {
-            self.player_position.y -= self.player_speed * delta_time;
-            self.player_position.y = self.player_position.y.max(0.0);
+        let position = Vector2::new(self.screen_width as f32 / 2.0, self.screen_height as f32 / 2.0);
+        let direction = get_random_direction(&mut self.rng);
+        self.balls.push(Ball::new(position, direction));
+        emit(GameEvent::Serve { position, direction });
+    }
+
+    fn record_snapshot(&mut self) {
+        let length = (self.instant_replay.seconds * TICK_RATE) as usize;
+        if length == 0 {
+            return;
         }
-        else if rl.is_key_down(KeyboardKey::KEY_S) {
-            self.player_position.y += self.player_speed * delta_time;
-            self.player_position.y = self.player_position.y.min(self.screen_height as f32 - self.player_size.y);
+        while self.rally.len() >= length {
+            self.rally.pop_front();
         }
+        self.rally.push_back(Snapshot {
+            ball_positions: self.balls.iter().map(|ball| ball.position).collect(),
+            player_position: self.player_position,
+            enemy_position: self.enemy_ai.position,
+            top_position: self.top_ai.position,
+            bottom_position: self.bottom_ai.position,
+        });
+    }
+
+    pub fn has_goal_replay(&self) -> bool {
+        self.goal_scored && !self.rally.is_empty()
+    }
+
+    /// The end of the rally that ended in the last goal, oldest first.
+    pub fn take_goal_replay(&mut self) -> Vec<Snapshot> {
+        self.goal_scored = false;
+        self.rally.iter().cloned().collect()
+    }
 
-        // check arrow up and down keys
-        if rl.is_key_down(KeyboardKey::KEY_UP) {
-            self.player_position.y -= self.player_speed * delta_time;
-            self.player_position.y = self.player_position.y.max(0.0);
+    /// Draws the arena as it was in `snapshot`, with the current score.
+    pub fn draw_snapshot(&mut self, d: &mut RaylibDrawHandle, snapshot: &Snapshot) {
+        self.draw_center_line(d);
+        for position in snapshot.ball_positions.iter() {
+            self.theme.draw_ball(d, *position, self.ball_radius);
+        }
+        let positions = [snapshot.player_position, snapshot.enemy_position, snapshot.top_position, snapshot.bottom_position];
+        self.draw_paddles(d, positions);
+        self.draw_score(d);
+    }
+
+    fn update_player_movement(&mut self, input: PaddleInput, delta_time: f32) {
+        self.player_position.y += input.direction * self.player_speed * delta_time;
+        self.player_position.y = self.player_position.y.clamp(0.0, self.screen_height as f32 - self.player_size.y);
+    }
+
+    /// Where a human controlled paddle at `y` ends up after one tick of `input`.
+    pub fn step_paddle(&self, y: f32, input: PaddleInput) -> f32 {
+        (y + input.direction * self.player_speed * TICK).clamp(0.0, self.screen_height as f32 - self.player_size.y)
+    }
+
+    // like step_paddle for the top and bottom paddles
+    fn step_paddle_across(&self, x: f32, input: PaddleInput) -> f32 {
+        (x + input.direction * self.player_speed * TICK).clamp(0.0, self.screen_width as f32 - self.top_ai.size.x)
+    }
+
+    // position and size of the paddle guarding `side`
+    fn paddle(&self, side: Side) -> (Vector2, Vector2) {
+        match side {
+            Side::Left => (self.player_position, self.player_size),
+            Side::Right => (self.enemy_ai.position, self.enemy_ai.size),
+            Side::Top => (self.top_ai.position, self.top_ai.size),
+            Side::Bottom => (self.bottom_ai.position, self.bottom_ai.size),
         }
-        else if rl.is_key_down(KeyboardKey::KEY_DOWN) {
-            self.player_position.y += self.player_speed * delta_time;
-            self.player_position.y = self.player_position.y.min(self.screen_height as f32 - self.player_size.y);
+    }
+
+    /// Whether a paddle guards `side`, the ball scores there and bounces off the other sides.
+    pub fn is_defended(&self, side: Side) -> bool {
+        match self.mode {
+            GameMode::Classic | GameMode::MultiBall => !side.is_horizontal(),
+            // a side that is out of lives turns into a wall
+            GameMode::FourPlayer => self.four_player.scoring == FourPlayerScoring::Points || self.score[side.index()] > 0,
         }
+    }
 
-        // check gamepad
-        if rl.is_gamepad_available(0) {
-            // check left stick y axis
-            let y = rl.get_gamepad_axis_movement(0, GamepadAxis::GAMEPAD_AXIS_LEFT_Y);
-            if y < -0.5 {
-                self.player_position.y -= self.player_speed * delta_time;
-                self.player_position.y = self.player_position.y.max(0.0);
-            }
-            else if y > 0.5 {
-                self.player_position.y += self.player_speed * delta_time;
-                self.player_position.y = self.player_position.y.min(self.screen_height as f32 - self.player_size.y);
-            }
+    /// The side that won the match, if one did already.
+    pub fn winner(&self) -> Option<Side> {
+        match self.mode {
+            GameMode::Classic | GameMode::MultiBall => [Side::Left, Side::Right].into_iter().find(|side| self.score[side.index()] >= WINNING_SCORE),
+            GameMode::FourPlayer => match self.four_player.scoring {
+                FourPlayerScoring::Lives => {
+                    let mut alive = Side::ALL.into_iter().filter(|side| self.score[side.index()] > 0);
+                    match (alive.next(), alive.next()) {
+                        (Some(last), None) => Some(last),
+                        _ => None,
+                    }
+                }
+                FourPlayerScoring::Points => Side::ALL.into_iter().find(|side| self.score[side.index()] >= self.four_player.points),
+            },
+        }
+    }
 
-            // check d-pad up and down
-            if rl.is_gamepad_button_down(0, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP) {
-                self.player_position.y -= self.player_speed * delta_time;
-                self.player_position.y = self.player_position.y.max(0.0);
-            }
-            else if rl.is_gamepad_button_down(0, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN) {
-                self.player_position.y += self.player_speed * delta_time;
-                self.player_position.y = self.player_position.y.min(self.screen_height as f32 - self.player_size.y);
-            }
+    pub fn save_state(&self) -> SimulationState {
+        SimulationState {
+            balls: self.balls.clone(),
+            ball_speed: self.ball_speed,
+            spawn_timer: self.spawn_timer,
+            spawn_hits: self.spawn_hits,
+            player_position: self.player_position,
+            enemy_ai: self.enemy_ai.clone(),
+            top_ai: self.top_ai.clone(),
+            bottom_ai: self.bottom_ai.clone(),
+            score: self.score,
+            last_hit: self.last_hit,
+            serve_timer: self.serve_timer,
+            go_timer: self.go_timer,
+            paused: self.paused,
+            match_over: self.match_over,
+            rng: self.rng.clone(),
         }
     }
 
+    pub fn load_state(&mut self, state: &SimulationState) {
+        let state = state.clone();
+        self.balls = state.balls;
+        self.ball_speed = state.ball_speed;
+        self.spawn_timer = state.spawn_timer;
+        self.spawn_hits = state.spawn_hits;
+        self.player_position = state.player_position;
+        self.enemy_ai = state.enemy_ai;
+        self.top_ai = state.top_ai;
+        self.bottom_ai = state.bottom_ai;
+        self.score = state.score;
+        self.last_hit = state.last_hit;
+        self.serve_timer = state.serve_timer;
+        self.go_timer = state.go_timer;
+        self.paused = state.paused;
+        self.match_over = state.match_over;
+        self.rng = state.rng;
+    }
+
+    pub fn match_state(&self) -> MatchState {
+        MatchState {
+            // networked matches are always classic ones with a single ball
+            ball_position: self.balls[0].position,
+            ball_velocity: self.balls[0].velocity,
+            left_paddle: self.player_position.y,
+            right_paddle: self.enemy_ai.position.y,
+            score: (self.score[0], self.score[1]),
+            serve_timer: self.serve_timer,
+            go_timer: self.go_timer,
+            paused: self.paused,
+            match_over: self.match_over,
+        }
+    }
+
+    /// Shows `state` without simulating anything, for clients of a networked match.
+    pub fn apply_match_state(&mut self, state: &MatchState) {
+        let ball = &mut self.balls[0];
+        // the trail follows the ball unless it jumped back to the center
+        if state.ball_position.distance_to(ball.position) > self.screen_width as f32 / 4.0 {
+            ball.history.clear();
+        }
+        ball.history.push_front(state.ball_position);
+        ball.history.truncate(self.trail.length);
+
+        ball.position = state.ball_position;
+        ball.velocity = state.ball_velocity;
+        self.player_position.y = state.left_paddle;
+        self.enemy_ai.position.y = state.right_paddle;
+        self.score = [state.score.0, state.score.1, 0, 0];
+        self.serve_timer = state.serve_timer;
+        self.go_timer = state.go_timer;
+        self.paused = state.paused;
+        self.match_over = state.match_over;
+    }
+
     pub fn draw(&mut self, d: &mut RaylibDrawHandle) {
         // draw center line
         self.draw_center_line(d);
         // draw ball
         self.draw_ball(d);
-        // draw player
-        self.draw_player(d);
-        // draw enemy
-        self.draw_enemy(d);
+        // draw particles
+        self.particles.draw(d);
+        // draw paddles
+        let positions = [self.player_position, self.enemy_ai.position, self.top_ai.position, self.bottom_ai.position];
+        self.draw_paddles(d, positions);
         // draw score
         self.draw_score(d);
-        // draw countdown
-        self.draw_countdown(d);
+        // draw countdown, not while the game over screen is shown
+        if !self.match_over {
+            self.draw_countdown(d);
+        }
     }
 
     fn draw_countdown(&mut self, d: &mut RaylibDrawHandle) {
-        // draw countdown format 3 2 1 GO
-        let time = get_time();
-        let time_since_last_score = time - self.time_since_last_score;
-        let countdown = 3.0 - time_since_last_score;
-        if countdown > 0.0 {
-            d.draw_circle(self.screen_width as i32 / 2,
-                          self.screen_height as i32 / 2,
-                          100.0,
-                          Color::WHITE);
-
-            // draw text with format 3 2 1
-            let text = format!("{:.0}", countdown);
-            let text_width = measure_text(&text, 100) as f32;
-            let text_position = Vector2::new(self.screen_width as f32 / 2.0 - text_width / 2.0,
-                                             self.screen_height as f32 / 2.0 - 50.0);
-            d.draw_text(&text,
-                        text_position.x as i32,
-                        text_position.y as i32,
-                        100,
-                        Color::BLACK);
+        if !self.serve.show_countdown {
+            return;
         }
-    }
 
-    fn draw_enemy(&mut self, d: &mut RaylibDrawHandle) {
+        // draw countdown format 3 2 1 GO, each number pops in large and fades out
+        let (text, timeline, time) = if self.paused && self.serve_timer > 0.0 {
+            let number = self.serve_timer.ceil();
+            // how far into this number's second we are
+            let time = 1.0 - (self.serve_timer - (number - 1.0));
+            (format!("{}", number as u32), &self.number_timeline, time)
+        } else if self.go_timer > 0.0 {
+            // GO grows and fades while the ball is already moving
+            (String::from("GO"), &self.go_timeline, GO_TIME - self.go_timer)
+        } else {
+            return;
+        };
+        let scale = timeline.sample("scale", time);
+        let alpha = timeline.sample("alpha", time);
 
-        d.draw_rectangle_v(self.enemy_ai.position,
-                           self.enemy_ai.size,
-                           Color::WHITE);
+        let center = Vector2::new(self.screen_width as f32 / 2.0, self.screen_height as f32 / 2.0);
+        d.draw_circle_v(center, 100.0 * scale, self.theme.colors.text.fade(alpha));
+        fontmanager::draw_centered(self.theme.font(), &text, center, 100.0 * scale, self.theme.colors.background.fade(alpha));
     }
 
-    fn draw_player(&mut self, d: &mut RaylibDrawHandle) {
-        d.draw_rectangle_v(self.player_position,
-                           self.player_size,
-                           Color::WHITE);
+    // `positions` by `Side::index`, every other paddle has the color of the right one
+    fn draw_paddles(&mut self, d: &mut RaylibDrawHandle, positions: [Vector2; 4]) {
+        for side in Side::ALL {
+            let (_, size) = self.paddle(side);
+            if self.is_defended(side) {
+                let color = if side == Side::Left { self.theme.colors.left_paddle } else { self.theme.colors.right_paddle };
+                self.theme.draw_paddle(d, positions[side.index()], size, color);
+            } else if self.mode == GameMode::FourPlayer {
+                // the wall that replaced a side that is out
+                let (width, height) = (self.screen_width as f32, self.screen_height as f32);
+                let (position, size) = match side {
+                    Side::Left => (Vector2::zero(), Vector2::new(4.0, height)),
+                    Side::Right => (Vector2::new(width - 4.0, 0.0), Vector2::new(4.0, height)),
+                    Side::Top => (Vector2::zero(), Vector2::new(width, 4.0)),
+                    Side::Bottom => (Vector2::new(0.0, height - 4.0), Vector2::new(width, 4.0)),
+                };
+                d.draw_rectangle_v(position, size, self.theme.colors.center_line);
+            }
+        }
     }
 
     fn draw_score(&mut self, d: &mut RaylibDrawHandle) {
-
-        // draw score left of the center screen
-        d.draw_text(&format!("{}", self.score.0),
-                    (self.screen_width as f32 / 2.0 - 100.0) as i32,
-                    50.0 as i32,
-                    100,
-                    Color::WHITE,
-        );
-
-        // draw score right of the center screen
-        d.draw_text(&format!("{}", self.score.1),
-                    (self.screen_width as f32 / 2.0 + 50.0) as i32,
-                    50.0 as i32,
-                    100,
-                    Color::WHITE,
-        );
+        if self.mode == GameMode::FourPlayer {
+            // each side's score in front of its goal
+            let (width, height) = (self.screen_width as f32, self.screen_height as f32);
+            for side in Side::ALL {
+                let center = match side {
+                    Side::Left => Vector2::new(100.0, height / 2.0),
+                    Side::Right => Vector2::new(width - 100.0, height / 2.0),
+                    Side::Top => Vector2::new(width / 2.0, 100.0),
+                    Side::Bottom => Vector2::new(width / 2.0, height - 100.0),
+                };
+                self.theme.draw_side_score(d, self.score[side.index()], center);
+            }
+            return;
+        }
+        // score left and right of the center line, aligned so multi-digit scores stay centered
+        self.theme.draw_score(d, (self.score[0], self.score[1]), self.screen_width);
     }
 
     fn draw_center_line(&mut self, d: &mut RaylibDrawHandle) {
-        // draw dotted line in the middle of the screen to divide the field
-        let mut i = 0;
-        while i < self.screen_height {
-            // ring width is 5 pixels
-            d.draw_rectangle(self.screen_width as i32 / 2,
-                             i,
-                             5,
-                             10,
-                             Color::WHITE);
-            i += 20;
-        }
+        // draw the line in the middle of the screen to divide the field
+        self.theme.draw_center_line(d, self.screen_width, self.screen_height);
+    }
+
+    pub fn draw_background(&self, d: &mut RaylibDrawHandle) {
+        self.theme.draw_background(d, self.screen_width, self.screen_height);
     }
 
     fn draw_ball(&mut self, d: &mut RaylibDrawHandle) {
-        // draw ball
-        d.draw_circle_v(self.ball_position, self.ball_radius, Color::WHITE);
+        for ball in self.balls.iter() {
+            draw_trail(d, &ball.history, self.ball_radius, self.theme.colors.ball, &self.trail);
+            // draw ball
+            self.theme.draw_ball(d, ball.position, self.ball_radius);
+        }
     }
 
     fn check_collision(&mut self) {
-        self.check_wall_collision();
-        self.check_player_collision();
-        self.check_enemy_collision();
+        // the balls are taken out while they are checked, a goal can serve the last one again
+        let mut balls = std::mem::take(&mut self.balls);
+        let mut i = 0;
+        while i < balls.len() {
+            let last = balls.len() == 1;
+            // a ball that scored leaves the rally while others are still in play
+            if self.check_wall_collision(&mut balls[i], last) && !last {
+                balls.remove(i);
+                // the balls still in play don't score after the winning goal
+                if self.match_over {
+                    break;
+                }
+                continue;
+            }
+            for side in Side::ALL {
+                if self.is_defended(side) {
+                    self.check_paddle_collision(&mut balls[i], side);
+                }
+            }
+            i += 1;
+        }
+        self.balls = balls;
     }
 
-    fn check_enemy_collision(&mut self) {
-        // check if ball is in player area only allow collision if ball is moving towards the player
-        let collided = check_ball_collision(self.ball_position,
-                                                      self.ball_radius,
-                                                      self.ball_velocity,
-                                                      self.enemy_ai.position,
-                                                      self.enemy_ai.size,
-                                                      false);
+    fn check_paddle_collision(&mut self, ball: &mut Ball, side: Side) {
+        let (paddle_position, paddle_size) = self.paddle(side);
+        // check if ball is in paddle area only allow collision if ball is moving towards the paddle
+        let collided = check_ball_collision(ball.position,
+                                            self.ball_radius,
+                                            ball.velocity,
+                                            paddle_position,
+                                            paddle_size,
+                                            side);
+        if !collided {
+            return;
+        }
 
-        if collided {
-            // set ball position to the edge of the enemy
-            self.ball_position.x = self.enemy_ai.position.x - self.enemy_ai.size.x - self.ball_radius / 2.0;
+        // set ball position to the edge of the paddle
+        match side {
+            Side::Left => ball.position.x = paddle_position.x + paddle_size.x + self.ball_radius,
+            Side::Right => ball.position.x = paddle_position.x - paddle_size.x - self.ball_radius / 2.0,
+            Side::Top => ball.position.y = paddle_position.y + paddle_size.y + self.ball_radius,
+            Side::Bottom => ball.position.y = paddle_position.y - self.ball_radius,
+        }
 
-            // reflect ball velocity
-            // self.ball_velocity.x *= -1.0;
-            self.ball_velocity = reflect_ball(self.ball_velocity,
-                                              self.ball_position,
-                         self.enemy_ai.position - self.enemy_ai.size / 2.0);
+        // the side paddles aim from above their corner as they always did, the flat ones from their middle
+        let paddle_center = if side.is_horizontal() { paddle_position + paddle_size / 2.0 } else { paddle_position - paddle_size / 2.0 };
+        ball.velocity = reflect_ball(ball.velocity, ball.position, paddle_center, side);
+        self.last_hit = Some(side);
+        self.spawn_hits += 1;
 
-            // play bounce sound
-            play_sfx(SoundType::Bounce, 0.5, 1.0);
-        }
+        emit(GameEvent::PaddleHit {
+            side,
+            position: ball.position,
+            offset: paddle_offset(ball.position, paddle_position, paddle_size, side),
+            // returns off the paddle edges leave steeper and faster
+            speed: self.ball_speed * ball.velocity.length(),
+        });
     }
 
-    fn check_player_collision(&mut self) {
-
-        // check if ball is in player area only allow collision if ball is moving towards the player
-        let collided = check_ball_collision(self.ball_position,
-                                                              self.ball_radius,
-                                                              self.ball_velocity,
-                                                              self.player_position,
-                                                              self.player_size,
-                                                              true);
+    // true when `ball` scored, the `last` ball in play is served again right away
+    fn check_wall_collision(&mut self, ball: &mut Ball, last: bool) -> bool {
+        // the ball scores on the sides with a paddle and bounces off the others
+        for side in Side::ALL {
+            let defended = self.is_defended(side);
+            // goals count once the ball is out of the arena, walls push it back at their edge
+            let margin = if defended { -self.ball_radius / 2.0 } else { self.ball_radius };
+            let past = match side {
+                Side::Left => ball.position.x < margin,
+                Side::Right => ball.position.x > self.screen_width as f32 - margin,
+                Side::Top => ball.position.y < margin,
+                Side::Bottom => ball.position.y > self.screen_height as f32 - margin,
+            };
+            if !past {
+                continue;
+            }
 
-        if collided {
-            // set ball position to the edge of the player
-            self.ball_position.x = self.player_position.x + self.player_size.x + self.ball_radius;
+            if defended {
+                self.concede(ball, side, last);
+                return true;
+            }
 
-            // reflect ball velocity
-            // self.ball_velocity.x *= -1.0;
-            self.ball_velocity = reflect_ball(self.ball_velocity,
-                                              self.ball_position,
-                         self.player_position - self.player_size / 2.0);
+            // set position to the wall
+            match side {
+                Side::Left => ball.position.x = self.ball_radius,
+                Side::Right => ball.position.x = self.screen_width as f32 - self.ball_radius,
+                Side::Top => ball.position.y = self.ball_radius,
+                Side::Bottom => ball.position.y = self.screen_height as f32 - self.ball_radius,
+            }
+            if side.is_horizontal() {
+                ball.velocity.y *= -1.0;
+            } else {
+                ball.velocity.x *= -1.0;
+            }
 
-            // play bounce sound
-            play_sfx(SoundType::Bounce, 0.5, 1.0);
+            emit(GameEvent::WallBounce { side, position: ball.position, velocity: ball.velocity });
         }
+        false
     }
 
-    fn check_wall_collision(&mut self) {
-        // check collision with walls
-        // check for collision with left and right walls
-        if self.ball_position.x < -self.ball_radius / 2.0 {
+    // `ball` left the arena through `side`, the rally ends with the `last` ball in play
+    fn concede(&mut self, ball: &mut Ball, side: Side, last: bool) {
+        let goal_position = ball.position;
+
+        if last {
             // set ball position to center
-            self.ball_position.x = self.screen_width as f32 / 2.0;
-            self.ball_position.y = self.screen_height as f32 / 2.0;
+            ball.position.x = self.screen_width as f32 / 2.0;
+            ball.position.y = self.screen_height as f32 / 2.0;
+            ball.history.clear();
 
-            // randomize ball velocity via fastrand vector2
-            self.ball_velocity = get_random_direction();
+            // serve toward the player who conceded
+            ball.velocity = get_serve_direction(side, &mut self.rng);
+        }
 
-            // add score to right player (enemy)
-            self.score.1 += 1;
-            self.time_since_last_score = get_time();
-            play_sfx(SoundType::EnemyScored, 0.5, 1.0);
+        let scorer = match self.mode {
+            GameMode::Classic | GameMode::MultiBall => Some(if side == Side::Left { Side::Right } else { Side::Left }),
+            GameMode::FourPlayer => match self.four_player.scoring {
+                FourPlayerScoring::Lives => {
+                    self.score[side.index()] -= 1;
+                    None
+                }
+                // nobody gets a point for an own goal or a serve nobody touched
+                FourPlayerScoring::Points => self.last_hit.filter(|hitter| *hitter != side),
+            },
+        };
+        if let Some(scorer) = scorer {
+            self.score[scorer.index()] += 1;
         }
-        else if self.ball_position.x > self.screen_width as f32 + self.ball_radius / 2.0 {
-            // set ball position to center
-            self.ball_position.x = self.screen_width as f32 / 2.0;
-            self.ball_position.y = self.screen_height as f32 / 2.0;
+        if last {
+            self.start_serve();
+        }
+        self.point_scored(scorer, side, goal_position, last);
+    }
 
-            // randomize ball velocity via fastrand vector2
-            self.ball_velocity = get_random_direction();
+    fn point_scored(&mut self, side: Option<Side>, conceded: Side, position: Vector2, last: bool) {
+        emit(GameEvent::Scored { side, conceded, position, score: self.score });
+        if let Some(winner) = self.winner() {
+            emit(GameEvent::MatchWon { side: winner, score: self.score });
+            self.match_over = true;
+        }
+        // the instant replay waits for the end of the rally or the match, other balls may still be in play
+        if last || self.match_over {
+            self.goal_scored = self.instant_replay.enabled;
+        }
+    }
+}
+
+/// The match itself, pauses on escape and shows the results once it is won.
+pub struct PlayingScene {
+    recorder: Recorder,
+    // seed of the next match, a random one when not set
+    pub seed: Option<u64>,
+}
 
-            // add score to left player
-            self.score.0 += 1;
-            self.time_since_last_score = get_time();
-            play_sfx(SoundType::PlayerScored, 0.5, 1.0);
+impl PlayingScene {
+    pub fn new(settings: &Settings) -> PlayingScene {
+        PlayingScene {
+            recorder: Recorder::new(&settings.replay.directory),
+            seed: None,
         }
+    }
+}
 
-        // check for collision with top and bottom walls
-        if self.ball_position.y < self.ball_radius {
-            // set position to the top wall
-            self.ball_position.y = self.ball_radius;
-            self.ball_velocity.y *= -1.0;
+impl Scene for PlayingScene {
+    fn enter(&mut self, ctx: &mut Context) {
+        // resizing the window mid-match would change the simulation, the arena is scaled instead
+        ctx.game.fixed_arena = true;
+        // options changed during the last match take effect now
+        ctx.game.serve = ctx.settings.serve.clone();
+        match self.seed.take() {
+            Some(seed) => ctx.game.start_match(seed),
+            None => ctx.game.reset_match(),
+        }
+        // replays only hold the inputs of one player, the other sides of a four player match could be people
+        if ctx.settings.replay.record && ctx.game.mode != GameMode::FourPlayer {
+            self.recorder.directory = ctx.settings.replay.directory.clone();
+            self.recorder.start(ctx.game, ctx.settings);
+        }
+    }
 
-            // play bounce sound
-            play_sfx(SoundType::Bounce, 0.5, 1.0);
+    fn exit(&mut self, ctx: &mut Context) {
+        ctx.game.fixed_arena = false;
+        self.recorder.finish();
+    }
+
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand {
+        // a hit-stop holds the simulation for a few frames
+        if !ctx.camera_effects.is_frozen() {
+            let input = poll_player_input(rl);
+            let ticks = match ctx.game.mode {
+                GameMode::Classic | GameMode::MultiBall => ctx.game.update(input),
+                // the second to fourth gamepad take the right, top and bottom paddles
+                GameMode::FourPlayer => ctx.game.update_players([
+                    Some(input),
+                    poll_gamepad_input(rl, 1, false),
+                    poll_gamepad_input(rl, 2, true),
+                    poll_gamepad_input(rl, 3, true),
+                ]),
+            };
+            self.recorder.record(input, ticks);
         }
-        else if self.ball_position.y > self.screen_height as f32 - self.ball_radius {
-            // set position to the bottom wall
-            self.ball_position.y = self.screen_height as f32 - self.ball_radius;
-            self.ball_velocity.y *= -1.0;
 
-            // play bounce sound
-            play_sfx(SoundType::Bounce, 0.5, 1.0);
+        if ctx.game.has_goal_replay() {
+            // the game over screen follows the replay of the winning goal
+            SceneCommand::Push(GameState::InstantReplay, Transition::Cut)
+        } else if ctx.game.is_match_over() {
+            SceneCommand::Push(GameState::GameOver, Transition::Cut)
+        } else if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) ||
+            rl.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT) {
+            SceneCommand::Push(GameState::Paused, Transition::Cut)
+        } else {
+            SceneCommand::None
         }
     }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        // draw the arena through the shaking camera
+        let arena = (ctx.game.screen_width, ctx.game.screen_height);
+        let camera = ctx.camera_effects.fitted_camera(arena, (d.get_screen_width(), d.get_screen_height()));
+        unsafe { ffi::BeginMode2D(camera); }
+        ctx.game.draw(d);
+        unsafe { ffi::EndMode2D(); }
+    }
 }
 
 fn check_ball_collision(ball_position: Vector2,
@@ -342,29 +951,50 @@ fn check_ball_collision(ball_position: Vector2,
                         ball_velocity: Vector2,
                         player_position: Vector2,
                         player_size: Vector2,
-                        is_player: bool) -> bool {
+                        side: Side) -> bool {
+    let moving_toward = match side {
+        Side::Left => ball_velocity.x < 0.0,
+        Side::Right => ball_velocity.x > 0.0,
+        Side::Top => ball_velocity.y < 0.0,
+        Side::Bottom => ball_velocity.y > 0.0,
+    };
     if ball_position.x - ball_radius < player_position.x + player_size.x &&
         ball_position.x + ball_radius > player_position.x &&
         ball_position.y - ball_radius < player_position.y + player_size.y &&
         ball_position.y + ball_radius > player_position.y
         /* ball is in direction */ &&
-        (is_player && ball_velocity.x < 0.0 || !is_player && ball_velocity.x > 0.0) {
+        moving_toward {
         return true;
     }
     return false;
 }
 
-fn reflect_ball(v_in: Vector2, ball_position: Vector2, paddle_center: Vector2) -> Vector2 {
+// where the ball touched the paddle, -1.0 at the top (or left) edge and 1.0 at the bottom (or right) edge
+fn paddle_offset(ball_position: Vector2, paddle_position: Vector2, paddle_size: Vector2, side: Side) -> f32 {
+    if side.is_horizontal() {
+        let center = paddle_position.x + paddle_size.x / 2.0;
+        return ((ball_position.x - center) / (paddle_size.x / 2.0)).clamp(-1.0, 1.0);
+    }
+    let center = paddle_position.y + paddle_size.y / 2.0;
+    ((ball_position.y - center) / (paddle_size.y / 2.0)).clamp(-1.0, 1.0)
+}
+
+fn reflect_ball(v_in: Vector2, ball_position: Vector2, paddle_center: Vector2, side: Side) -> Vector2 {
     let mut v_out = v_in;
-    // bounce off paddle Y velocity is the difference between the ball position and the paddle center normalized
     let diff = (ball_position - paddle_center).normalized();
+    if side.is_horizontal() {
+        // bounce off flat paddle X velocity is the difference between the ball position and the paddle center normalized
+        v_out.x = diff.x;
+        v_out.y *= -1.0;
+        return v_out;
+    }
+    // bounce off paddle Y velocity is the difference between the ball position and the paddle center normalized
     v_out.y = diff.y * -1.0;
     v_out.x *= -1.0;
     v_out
 }
 
-fn get_random_direction() -> Vector2 {
-    let mut rng = rand::thread_rng();
+fn get_random_direction(rng: &mut StdRng) -> Vector2 {
     // generate random direction within a 45 degree angle
     let angle: f32 = rng.gen_range(0.0..45.0);
     let angle: f32 = angle.to_radians();
@@ -381,9 +1011,14 @@ fn get_random_direction() -> Vector2 {
     return direction;
 }
 
-fn get_time() -> f64 {
-    unsafe {
-        time::TIME
+// random direction within a 45 degree angle heading toward `side`
+fn get_serve_direction(side: Side, rng: &mut StdRng) -> Vector2 {
+    let direction = get_random_direction(rng);
+    match side {
+        Side::Left => Vector2::new(-direction.x.abs(), direction.y),
+        Side::Right => Vector2::new(direction.x.abs(), direction.y),
+        Side::Top => Vector2::new(direction.y, -direction.x.abs()),
+        Side::Bottom => Vector2::new(direction.y, direction.x.abs()),
     }
 }
 
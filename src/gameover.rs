@@ -0,0 +1,78 @@
+use raylib::prelude::*;
+use crate::events::Side;
+use crate::game::{Game, GameMode};
+use crate::gamestate::GameState;
+use crate::scene::{Context, Scene, SceneCommand, Transition};
+use crate::stats::Stats;
+
+/// Whether a confirm button was pressed for a rematch.
+pub fn wants_rematch(rl: &RaylibHandle) -> bool {
+    // any confirm button starts a rematch
+    rl.is_key_pressed(KeyboardKey::KEY_ENTER) ||
+        rl.is_key_pressed(KeyboardKey::KEY_SPACE) ||
+        rl.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN)
+}
+
+/// Draws the winner, the score and the stats of the match over the frozen arena.
+pub fn draw(d: &mut RaylibDrawHandle, game: &Game, stats: &Stats) {
+    let colors = game.theme.colors;
+    // dim the frozen field behind the results
+    d.draw_rectangle(0, 0, game.screen_width, game.screen_height, colors.background.fade(0.7));
+
+    let title = match (game.winner(), game.local_side) {
+        (Some(winner), Some(local)) if winner == local => String::from("YOU WIN"),
+        (Some(winner), None) => format!("{} WINS", winner.name()),
+        _ => String::from("YOU LOSE"),
+    };
+    // left - right, every side by name in a four player match
+    let four_player = game.mode == GameMode::FourPlayer;
+    let per_side = |values: [String; 4]| if four_player {
+        Side::ALL.iter().map(|side| format!("{} {}", side.name(), values[side.index()])).collect::<Vec<_>>().join("   ")
+    } else {
+        format!("{} - {}", values[0], values[1])
+    };
+    let lines = [
+        per_side(game.score().map(|score| score.to_string())),
+        format!("Longest rally: {}", stats.longest_rally),
+        format!("Paddle hits: {}", per_side(stats.hits.map(|hits| hits.to_string()))),
+        format!("Matches won: {}", per_side(stats.matches_won.map(|won| won.to_string()))),
+        String::from("Press ENTER for a rematch, ESC for the menu"),
+    ];
+
+    let mut y = game.screen_height / 2 - 200;
+    let title_width = measure_text(&title, 100);
+    d.draw_text(&title, game.screen_width / 2 - title_width / 2, y, 100, colors.text);
+    y += 150;
+
+    for line in lines.iter() {
+        let width = measure_text(line, 30);
+        d.draw_text(line, game.screen_width / 2 - width / 2, y, 30, colors.text);
+        y += 45;
+    }
+}
+
+/// Results drawn over the frozen arena.
+pub struct GameOverScene;
+
+impl Scene for GameOverScene {
+    fn update(&mut self, rl: &mut RaylibHandle, _ctx: &mut Context) -> SceneCommand {
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return SceneCommand::Reset(GameState::MainMenu, Transition::Fade);
+        }
+
+        // a rematch is a new match with its own replay
+        if wants_rematch(rl) {
+            SceneCommand::Reset(GameState::Playing, Transition::Cut)
+        } else {
+            SceneCommand::None
+        }
+    }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        draw(d, ctx.game, ctx.stats);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
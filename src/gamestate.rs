@@ -1,7 +1,18 @@
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum GameState {
     Playing,
     Credits,
     MainMenu,
+    Options,
     GameOver,
+    /// pause overlay on top of a match
+    Paused,
+    /// playback of a replay file
+    Replay,
+    /// slow motion replay of the rally that ended in a goal
+    InstantReplay,
+    /// hosting or joining a networked match
+    Lobby,
+    /// networked match against another machine
+    Online,
 }
\ No newline at end of file
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+// codes never get longer than 12 bits
+const MAX_CODES: u16 = 4096;
+const MIN_CODE_SIZE: u8 = 8;
+
+/// Writes an endlessly looping animated GIF89a, one full frame at a time.
+///
+/// Frames with at most 256 colors keep their exact colors, busier frames are quantized
+/// to a fixed 3-3-2 bit palette.
+pub struct GifEncoder<W: Write> {
+    writer: W,
+    width: u16,
+    height: u16,
+    // frame delay in hundredths of a second
+    delay: u16,
+}
+
+impl<W: Write> GifEncoder<W> {
+    pub fn new(mut writer: W, width: u16, height: u16, fps: u32) -> io::Result<GifEncoder<W>> {
+        writer.write_all(b"GIF89a")?;
+        writer.write_all(&width.to_le_bytes())?;
+        writer.write_all(&height.to_le_bytes())?;
+        // no global color table, every frame brings its own
+        writer.write_all(&[0x00, 0, 0])?;
+
+        // netscape extension, loop forever
+        writer.write_all(&[0x21, 0xff, 11])?;
+        writer.write_all(b"NETSCAPE2.0")?;
+        writer.write_all(&[3, 1, 0, 0, 0])?;
+
+        Ok(GifEncoder {
+            writer,
+            width,
+            height,
+            delay: (100.0 / fps.max(1) as f32).round().max(1.0) as u16,
+        })
+    }
+
+    /// Adds a frame from `width * height` RGBA pixels, alpha is ignored.
+    pub fn write_frame(&mut self, rgba: &[u8]) -> io::Result<()> {
+        let (palette, indices) = index_pixels(rgba);
+
+        // graphic control extension with the frame delay
+        self.writer.write_all(&[0x21, 0xf9, 4, 0x00])?;
+        self.writer.write_all(&self.delay.to_le_bytes())?;
+        self.writer.write_all(&[0, 0])?;
+
+        // image descriptor covering the whole canvas with a local 256 color table
+        self.writer.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.writer.write_all(&self.width.to_le_bytes())?;
+        self.writer.write_all(&self.height.to_le_bytes())?;
+        self.writer.write_all(&[0x87])?;
+        for color in palette.iter() {
+            self.writer.write_all(color)?;
+        }
+
+        self.writer.write_all(&[MIN_CODE_SIZE])?;
+        // image data goes out in sub-blocks of at most 255 bytes
+        for block in lzw_encode(&indices).chunks(255) {
+            self.writer.write_all(&[block.len() as u8])?;
+            self.writer.write_all(block)?;
+        }
+        self.writer.write_all(&[0])
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[0x3b])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn index_pixels(rgba: &[u8]) -> ([[u8; 3]; 256], Vec<u8>) {
+    let mut palette = [[0; 3]; 256];
+    let mut exact: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+
+    for pixel in rgba.chunks_exact(4) {
+        let color = [pixel[0], pixel[1], pixel[2]];
+        let next = exact.len();
+        let index = match exact.get(&color) {
+            Some(index) => *index,
+            None if next < 256 => {
+                exact.insert(color, next as u8);
+                palette[next] = color;
+                next as u8
+            }
+            // too many colors for an exact palette
+            None => return quantize(rgba),
+        };
+        indices.push(index);
+    }
+    (palette, indices)
+}
+
+// 3 bits red, 3 bits green and 2 bits blue
+fn quantize(rgba: &[u8]) -> ([[u8; 3]; 256], Vec<u8>) {
+    let mut palette = [[0; 3]; 256];
+    for (i, color) in palette.iter_mut().enumerate() {
+        *color = [
+            (((i >> 5) & 7) * 255 / 7) as u8,
+            (((i >> 2) & 7) * 255 / 7) as u8,
+            ((i & 3) * 255 / 3) as u8,
+        ];
+    }
+
+    let indices = rgba.chunks_exact(4)
+        .map(|pixel| (pixel[0] & 0xe0) | ((pixel[1] >> 3) & 0x1c) | (pixel[2] >> 6))
+        .collect();
+    (palette, indices)
+}
+
+// packs codes of growing size least significant bit first
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << MIN_CODE_SIZE;
+    let end = clear + 1;
+
+    let mut output = BitWriter { bytes: Vec::new(), buffer: 0, bits: 0 };
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut code_size = MIN_CODE_SIZE + 1;
+
+    output.write(clear, code_size);
+    let mut prefix = match indices.first() {
+        Some(index) => *index as u16,
+        None => {
+            output.write(end, code_size);
+            return output.finish();
+        }
+    };
+
+    for &index in &indices[1..] {
+        if let Some(code) = codes.get(&(prefix, index)) {
+            prefix = *code;
+            continue;
+        }
+
+        output.write(prefix, code_size);
+        if next_code < MAX_CODES {
+            codes.insert((prefix, index), next_code);
+            // the decoder widens its codes once it hands out this one
+            if next_code == 1 << code_size {
+                code_size += 1;
+            }
+            next_code += 1;
+        } else {
+            // the table is full, start over
+            output.write(clear, code_size);
+            codes.clear();
+            next_code = end + 1;
+            code_size = MIN_CODE_SIZE + 1;
+        }
+        prefix = index as u16;
+    }
+
+    output.write(prefix, code_size);
+    output.write(end, code_size);
+    output.finish()
+}
@@ -0,0 +1,92 @@
+use raylib::prelude::*;
+
+/// Paddle input for one simulation tick.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct PaddleInput {
+    /// -1.0 moves up, 1.0 moves down, left and right for the top and bottom paddles
+    pub direction: f32,
+}
+
+/// Reads keyboard and the first gamepad, every source can move the paddle.
+pub fn poll_player_input(rl: &RaylibHandle) -> PaddleInput {
+    // W and S keys and the arrow keys
+    let mut up = rl.is_key_down(KeyboardKey::KEY_W) || rl.is_key_down(KeyboardKey::KEY_UP);
+    let mut down = rl.is_key_down(KeyboardKey::KEY_S) || rl.is_key_down(KeyboardKey::KEY_DOWN);
+
+    // check gamepad
+    if rl.is_gamepad_available(0) {
+        // left stick y axis and d-pad up and down
+        let y = rl.get_gamepad_axis_movement(0, GamepadAxis::GAMEPAD_AXIS_LEFT_Y);
+        up |= y < -0.5 || rl.is_gamepad_button_down(0, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP);
+        down |= y > 0.5 || rl.is_gamepad_button_down(0, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN);
+    }
+
+    PaddleInput {
+        direction: down as i32 as f32 - up as i32 as f32,
+    }
+}
+
+/// Reads gamepad `gamepad` alone, None when it isn't connected so the AI can play instead.
+pub fn poll_gamepad_input(rl: &RaylibHandle, gamepad: i32, horizontal: bool) -> Option<PaddleInput> {
+    if !rl.is_gamepad_available(gamepad) {
+        return None;
+    }
+
+    // the left stick and the d-pad along the paddle
+    let (axis, back, forward) = if horizontal {
+        (GamepadAxis::GAMEPAD_AXIS_LEFT_X, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT)
+    } else {
+        (GamepadAxis::GAMEPAD_AXIS_LEFT_Y, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN)
+    };
+    let movement = rl.get_gamepad_axis_movement(gamepad, axis);
+    let back = movement < -0.5 || rl.is_gamepad_button_down(gamepad, back);
+    let forward = movement > 0.5 || rl.is_gamepad_button_down(gamepad, forward);
+
+    Some(PaddleInput {
+        direction: forward as i32 as f32 - back as i32 as f32,
+    })
+}
+
+/// Navigation for menus, one action per key press.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuInput {
+    None,
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back,
+}
+
+pub fn poll_menu_input(rl: &RaylibHandle) -> MenuInput {
+    let key = |keys: &[KeyboardKey]| keys.iter().any(|key| rl.is_key_pressed(*key));
+    let button = |button: GamepadButton| rl.is_gamepad_available(0) && rl.is_gamepad_button_pressed(0, button);
+
+    if key(&[KeyboardKey::KEY_W, KeyboardKey::KEY_UP]) || button(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP) {
+        MenuInput::Up
+    } else if key(&[KeyboardKey::KEY_S, KeyboardKey::KEY_DOWN]) || button(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN) {
+        MenuInput::Down
+    } else if key(&[KeyboardKey::KEY_A, KeyboardKey::KEY_LEFT]) || button(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT) {
+        MenuInput::Left
+    } else if key(&[KeyboardKey::KEY_D, KeyboardKey::KEY_RIGHT]) || button(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT) {
+        MenuInput::Right
+    } else if key(&[KeyboardKey::KEY_ENTER, KeyboardKey::KEY_SPACE]) || button(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN) {
+        MenuInput::Confirm
+    } else if key(&[KeyboardKey::KEY_ESCAPE, KeyboardKey::KEY_BACKSPACE]) || button(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT) {
+        MenuInput::Back
+    } else {
+        MenuInput::None
+    }
+}
+
+/// Any key, gamepad button or mouse button pressed this frame.
+pub fn any_pressed(rl: &RaylibHandle) -> bool {
+    unsafe {
+        // both return 0 when nothing was pressed
+        ffi::GetKeyPressed() != 0 ||
+            ffi::GetGamepadButtonPressed() > 0 ||
+            rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) ||
+            rl.is_mouse_button_pressed(MouseButton::MOUSE_RIGHT_BUTTON)
+    }
+}
@@ -12,6 +12,34 @@ mod audiohelper;
 mod floathelper;
 mod credits;
 mod gamestate;
+mod gameover;
+mod settings;
+mod synth;
+mod music;
+mod events;
+mod stats;
+mod input;
+mod trail;
+mod particles;
+mod colorhelper;
+mod camera;
+mod postprocess;
+mod theme;
+mod menu;
+mod fontmanager;
+mod sevensegment;
+mod tween;
+mod scene;
+mod replay;
+mod cli;
+mod gif;
+mod export;
+mod net;
+mod discovery;
+mod netplay;
+mod netsim;
+mod rollback;
+mod spectate;
 
 use std::collections::HashMap;
 use raylib::ffi::{InitAudioDevice, LoadImageFromMemory, LoadSound, LoadSoundFromWave, LoadWaveFromMemory, PlaySound, PlaySoundMulti, SetAudioStreamPitch, SetSoundVolume, SetWindowIcon};
@@ -19,21 +47,51 @@ use raylib::prelude::*;
 use crate::debug::DRAW_LIST;
 
 fn main() {
-    let (mut rl, thread) = init()
-        .vsync()
-        .size(1920, 1080)
+    let options = match cli::CliOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n{}", err, cli::USAGE);
+            return;
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    unsafe { netsim::CONDITIONS = options.conditions; }
+
+    // raw frames go to stdout, raylib must not log in between them
+    if options.export.as_ref().map_or(false, |export| export.format == export::ExportFormat::Raw) {
+        unsafe { raylib::ffi::SetTraceLogLevel(raylib::ffi::TraceLogLevel::LOG_NONE as i32); }
+    }
+    // exports render off-screen, the window is created hidden and never shows up
+    if options.export.is_some() {
+        unsafe { raylib::ffi::SetConfigFlags(raylib::ffi::ConfigFlags::FLAG_WINDOW_HIDDEN as u32); }
+    }
+
+    let mut builder = init();
+    builder.size(1920, 1080)
         .title("raylib [core] example - basic window")
-        .resizable()
-        .build();
+        .resizable();
+    // exports render as fast as they can, vsync would hold every frame back
+    if options.export.is_none() {
+        builder.vsync();
+    }
+    let (mut rl, thread) = builder.build();
 
-    // initialize raylib audio
-    unsafe { InitAudioDevice(); };
+    // initialize raylib audio, exports are silent
+    if options.export.is_none() {
+        unsafe { InitAudioDevice(); };
+    }
 
     // rl.set_target_fps(120);
     let mut state = rl.get_window_state();
-    state = state.set_vsync_hint(true)
-        .set_window_undecorated(true)
-        .set_fullscreen_mode(true);
+    state = state.set_window_undecorated(true);
+    if options.export.is_none() {
+        state = state.set_vsync_hint(true)
+            .set_fullscreen_mode(true);
+    }
     rl.set_window_state(state);
 
     unsafe {
@@ -45,28 +103,92 @@ fn main() {
         });
     }
 
-    // load sounds
-    let mut sound_map = HashMap::new();
-    sound_map.insert(audiosystem::SoundType::Bounce, unsafe {
-        load_from_memory!("..\\res\\sounds\\paddle_hit.wav")
-    });
-    sound_map.insert(audiosystem::SoundType::PlayerScored, unsafe {
-        load_from_memory!("..\\res\\sounds\\win.wav")
-    });
-    sound_map.insert(audiosystem::SoundType::EnemyScored, unsafe {
-        load_from_memory!("..\\res\\sounds\\lose.wav")
-    });
+    let mut settings = settings::Settings::load(settings::SETTINGS_PATH);
+    let mut theme_manager = theme::ThemeManager::new(&settings.theme.name);
+
+    let mut game = game::Game::new(&mut rl, &settings, theme_manager.current().clone());
+
+    // subscribers to gameplay events
+    let mut sfx_events = audiosystem::SfxEvents::new(game.screen_width as f32);
+    let mut stats = stats::Stats::new();
+    let mut camera_effects = camera::CameraEffects::new(&settings.effects);
+    let mut post_process = postprocess::PostProcess::new(&settings.post_process);
+
+    if let (Some(export), Some(path)) = (&options.export, &options.replay) {
+        let result = replay::Replay::load(path)
+            .and_then(|replay| export::export(&mut rl, &thread, &mut game, &mut camera_effects, &replay, export));
+        match result {
+            Ok(frames) => eprintln!("exported {} frames to {}", frames, export.path),
+            Err(err) => eprintln!("failed to export {}: {}", path, err),
+        }
+        return;
+    }
+
+    // load sounds from the selected theme, each SoundManager voice gets its own copy of the wave
+    let sound_map = audiosystem::load_theme(settings.audio.sound_theme);
 
     // initialize audiomanager with sounds from sound_map
-    let mut audio_manager = audiosystem::SoundManager::new(Some(sound_map));
+    let mut audio_manager = audiosystem::SoundManager::new(Some(sound_map), &settings.audio);
 
-    let mut game = game::Game::new(&mut rl);
-    game.time_since_last_score = 5.0;
-    let mut credits = credits::Credits::new();
+    let mut music_player = music::MusicPlayer::new(settings.music.clone());
+
+    // every game state is a scene, the game starts with the credits rolling over the main menu
+    let mut scenes = scene::SceneManager::new();
+    scenes.register(game::GameState::Credits, Box::new(credits::Credits::new()));
+    scenes.register(game::GameState::MainMenu, Box::new(menu::MainMenu::new()));
+    scenes.register(game::GameState::Options, Box::new(menu::OptionsMenu::new()));
+    let mut playing = game::PlayingScene::new(&settings);
+    playing.seed = options.seed;
+    scenes.register(game::GameState::Playing, Box::new(playing));
+    scenes.register(game::GameState::Paused, Box::new(menu::PauseMenu::new()));
+    scenes.register(game::GameState::GameOver, Box::new(gameover::GameOverScene));
+    scenes.register(game::GameState::InstantReplay, Box::new(replay::InstantReplayScene::new()));
+    let mut lobby = netplay::LobbyScene::new();
+    lobby.autostart = options.lobby.clone();
+    scenes.register(game::GameState::Lobby, Box::new(lobby));
+    scenes.register(game::GameState::Online, Box::new(netplay::OnlineScene::new()));
+    let mut net_session = None;
 
     rl.hide_cursor();
+    // escape goes back in the menus, the main menu has a quit entry
+    rl.set_exit_key(None);
+
+    // borrows everything the scenes work with for one call
+    macro_rules! context {
+        () => {
+            &mut scene::Context {
+                game: &mut game,
+                settings: &mut settings,
+                theme_manager: &mut theme_manager,
+                audio_manager: &mut audio_manager,
+                camera_effects: &mut camera_effects,
+                post_process: &mut post_process,
+                stats: &stats,
+                net: &mut net_session,
+            }
+        };
+    }
+
+    // a replay from the command line is played back right away, quitting returns to the shell
+    let replay = options.replay.as_deref().and_then(|path| {
+        replay::Replay::load(path)
+            .map_err(|err| eprintln!("failed to load replay {}: {}", path, err))
+            .ok()
+    });
+    match replay {
+        Some(replay) => {
+            scenes.register(game::GameState::Replay, Box::new(replay::ReplayScene::new(replay)));
+            scenes.run(scene::SceneCommand::Push(game::GameState::Replay, scene::Transition::Cut), context!());
+        }
+        None => {
+            scenes.run(scene::SceneCommand::Push(game::GameState::MainMenu, scene::Transition::Cut), context!());
+            // --host and --join skip the credits
+            let first = if options.lobby.is_some() { game::GameState::Lobby } else { game::GameState::Credits };
+            scenes.run(scene::SceneCommand::Push(first, scene::Transition::Cut), context!());
+        }
+    }
 
-    while !rl.window_should_close() {
+    while !rl.window_should_close() && !scenes.should_quit() {
         let time = &rl.get_time();
 
         // update time
@@ -76,11 +198,19 @@ fn main() {
         }
 
         // game.update(&mut rl, &thread);
-        if game.game_state == game::GameState::Playing {
-            game.update(&mut rl);
-        } else if game.game_state == game::GameState::Credits {
-            credits.update(&mut game);
+        // particles hold still during a hit-stop and while paused
+        let frozen = camera_effects.is_frozen() || scenes.current() == game::GameState::Paused;
+        if cfg!(debug_assertions) {
+            netsim::update_overlay(&rl);
+        }
+        scenes.update(&mut rl, context!());
+
+        sfx_events.arena_width = game.screen_width as f32;
+        events::dispatch(&mut [&mut sfx_events, &mut stats, &mut game.particles, &mut camera_effects]);
+        if !frozen {
+            game.particles.update(unsafe { time::DELTA_TIME });
         }
+        camera_effects.update(unsafe { time::DELTA_TIME });
         // game.update(&mut rl);
 
         let mut d = rl.begin_drawing(&thread);
@@ -88,25 +218,37 @@ fn main() {
         let bounds = (d.get_screen_width(), d.get_screen_height());
         game.set_screen_size(bounds.0, bounds.1);
 
-        d.clear_background(Color::BLACK);
+        // draw the scene off-screen so it can go through the post-process chain
+        post_process.hot_reload(unsafe { time::DELTA_TIME });
+        post_process.begin(bounds.0, bounds.1);
 
-        if game.game_state == game::GameState::Playing {
-            game.draw(&mut d);
-        }
-        else if game.game_state == game::GameState::Credits {
-            credits.draw_credits(&mut d);
-        }
+        game.draw_background(&mut d);
+
+        scenes.draw(&mut d, context!());
+
+        post_process.end(*time as f32);
         // game.draw(&mut d);
 
+        // switch playlists when the game state changed
+        music_player.set_state(scenes.current());
+
         // play audio effects on stack
         unsafe {
+            if !audiosystem::SOUND_STACK.is_empty() {
+                music_player.duck();
+            }
             for sound_handle in audiosystem::SOUND_STACK.drain(..) {
-                audio_manager.play(&sound_handle.sound_type, sound_handle.volume, sound_handle.pitch);
+                audio_manager.play(&sound_handle.sound_type,
+                                   sound_handle.volume,
+                                   sound_handle.pitch,
+                                   sound_handle.pan);
             }
         }
+        music_player.update(unsafe { time::DELTA_TIME });
 
         if cfg!(debug_assertions) {
             draw_debug(&time, &mut d);
+            netsim::draw_overlay(&mut d);
         }
 
         // draw FPS top left corner
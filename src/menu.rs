@@ -0,0 +1,373 @@
+use raylib::prelude::*;
+use crate::audiosystem::{self, SoundTheme};
+use crate::game::GameMode;
+use crate::gamestate::GameState;
+use crate::input::{poll_menu_input, MenuInput};
+use crate::postprocess::PostProcess;
+use crate::scene::{Context, Scene, SceneCommand, Transition};
+use crate::settings::{self, Settings};
+use crate::theme::Theme;
+use crate::time::DELTA_TIME;
+use crate::trail::TrailStyle;
+use crate::tween::{Ease, Timeline};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuAction {
+    None,
+    Play,
+    FourPlayers,
+    MultiBall,
+    Options,
+    Credits,
+    Online,
+    Quit,
+    Resume,
+    MainMenu,
+    Back,
+    /// an option was changed and has to be applied
+    SettingsChanged,
+}
+
+const MAIN_ITEMS: [(&str, MenuAction); 7] = [
+    ("PLAY", MenuAction::Play),
+    ("4 PLAYERS", MenuAction::FourPlayers),
+    ("MULTI-BALL", MenuAction::MultiBall),
+    ("ONLINE", MenuAction::Online),
+    ("OPTIONS", MenuAction::Options),
+    ("CREDITS", MenuAction::Credits),
+    ("QUIT", MenuAction::Quit),
+];
+
+pub struct MainMenu {
+    selected: usize,
+    highlight: Timeline,
+}
+
+impl MainMenu {
+    pub fn new() -> MainMenu {
+        MainMenu {
+            selected: 0,
+            highlight: highlight_timeline(),
+        }
+    }
+
+    pub fn navigate(&mut self, rl: &RaylibHandle) -> MenuAction {
+        self.highlight.update(unsafe { DELTA_TIME });
+        match poll_menu_input(rl) {
+            MenuInput::Up => self.selected = (self.selected + MAIN_ITEMS.len() - 1) % MAIN_ITEMS.len(),
+            MenuInput::Down => self.selected = (self.selected + 1) % MAIN_ITEMS.len(),
+            MenuInput::Confirm => return MAIN_ITEMS[self.selected].1,
+            _ => return MenuAction::None,
+        }
+        self.highlight.restart();
+        MenuAction::None
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, theme: &Theme) {
+        let items: Vec<String> = MAIN_ITEMS.iter().map(|(label, _)| label.to_string()).collect();
+        draw_menu(d, theme, "PONG50TH", &items, self.selected, self.highlight.value("scale"));
+    }
+}
+
+impl Scene for MainMenu {
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand {
+        match self.navigate(rl) {
+            MenuAction::Play => {
+                ctx.game.mode = GameMode::Classic;
+                SceneCommand::Replace(GameState::Playing, Transition::Wipe)
+            }
+            MenuAction::FourPlayers => {
+                ctx.game.mode = GameMode::FourPlayer;
+                SceneCommand::Replace(GameState::Playing, Transition::Wipe)
+            }
+            MenuAction::MultiBall => {
+                ctx.game.mode = GameMode::MultiBall;
+                SceneCommand::Replace(GameState::Playing, Transition::Wipe)
+            }
+            MenuAction::Options => SceneCommand::Push(GameState::Options, Transition::Fade),
+            MenuAction::Credits => SceneCommand::Push(GameState::Credits, Transition::Fade),
+            MenuAction::Online => SceneCommand::Push(GameState::Lobby, Transition::Fade),
+            MenuAction::Quit => SceneCommand::Quit,
+            _ => SceneCommand::None,
+        }
+    }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        MainMenu::draw(self, d, &ctx.game.theme);
+    }
+}
+
+const PAUSE_ITEMS: [(&str, MenuAction); 3] = [
+    ("RESUME", MenuAction::Resume),
+    ("OPTIONS", MenuAction::Options),
+    ("MAIN MENU", MenuAction::MainMenu),
+];
+
+/// Overlay on top of a paused match.
+pub struct PauseMenu {
+    selected: usize,
+    highlight: Timeline,
+}
+
+impl PauseMenu {
+    pub fn new() -> PauseMenu {
+        PauseMenu {
+            selected: 0,
+            highlight: highlight_timeline(),
+        }
+    }
+}
+
+impl Scene for PauseMenu {
+    fn enter(&mut self, _ctx: &mut Context) {
+        self.selected = 0;
+    }
+
+    fn update(&mut self, rl: &mut RaylibHandle, _ctx: &mut Context) -> SceneCommand {
+        self.highlight.update(unsafe { DELTA_TIME });
+        let action = match poll_menu_input(rl) {
+            MenuInput::Up => {
+                self.selected = (self.selected + PAUSE_ITEMS.len() - 1) % PAUSE_ITEMS.len();
+                self.highlight.restart();
+                MenuAction::None
+            }
+            MenuInput::Down => {
+                self.selected = (self.selected + 1) % PAUSE_ITEMS.len();
+                self.highlight.restart();
+                MenuAction::None
+            }
+            MenuInput::Confirm => PAUSE_ITEMS[self.selected].1,
+            MenuInput::Back => MenuAction::Resume,
+            _ => MenuAction::None,
+        };
+
+        match action {
+            MenuAction::Resume => SceneCommand::Pop(Transition::Cut),
+            MenuAction::Options => SceneCommand::Push(GameState::Options, Transition::Fade),
+            MenuAction::MainMenu => SceneCommand::Reset(GameState::MainMenu, Transition::Fade),
+            _ => SceneCommand::None,
+        }
+    }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        let theme = &ctx.game.theme;
+        // dim the frozen match below
+        d.draw_rectangle(0, 0, d.get_screen_width(), d.get_screen_height(), theme.colors.background.fade(0.7));
+        let items: Vec<String> = PAUSE_ITEMS.iter().map(|(label, _)| label.to_string()).collect();
+        draw_menu(d, theme, "PAUSED", &items, self.selected, self.highlight.value("scale"));
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum OptionItem {
+    Theme,
+    SoundTheme,
+    Trail,
+    Countdown,
+    ShowCountdown,
+    InstantReplay,
+    Particles,
+    Effects,
+    Shake,
+    HitStop,
+    Zoom,
+    PostProcess,
+    Back,
+}
+
+const OPTION_ITEMS: [OptionItem; 13] = [
+    OptionItem::Theme,
+    OptionItem::SoundTheme,
+    OptionItem::Trail,
+    OptionItem::Countdown,
+    OptionItem::ShowCountdown,
+    OptionItem::InstantReplay,
+    OptionItem::Particles,
+    OptionItem::Effects,
+    OptionItem::Shake,
+    OptionItem::HitStop,
+    OptionItem::Zoom,
+    OptionItem::PostProcess,
+    OptionItem::Back,
+];
+
+const TRAIL_STYLES: [TrailStyle; 4] = [TrailStyle::Off, TrailStyle::Circles, TrailStyle::Ribbon, TrailStyle::Phosphor];
+const SOUND_THEMES: [SoundTheme; 2] = [SoundTheme::Samples, SoundTheme::Retro];
+// longest serve countdown in seconds
+const MAX_COUNTDOWN: u32 = 5;
+// how much a slider moves per key press
+const SLIDER_STEP: f32 = 0.1;
+
+/// Edits the settings in place, left and right change the selected option.
+pub struct OptionsMenu {
+    selected: usize,
+    highlight: Timeline,
+}
+
+impl OptionsMenu {
+    pub fn new() -> OptionsMenu {
+        OptionsMenu {
+            selected: 0,
+            highlight: highlight_timeline(),
+        }
+    }
+
+    pub fn navigate(&mut self, rl: &RaylibHandle, settings: &mut Settings, theme_names: &[String]) -> MenuAction {
+        self.highlight.update(unsafe { DELTA_TIME });
+        let step = match poll_menu_input(rl) {
+            MenuInput::Up => {
+                self.selected = (self.selected + OPTION_ITEMS.len() - 1) % OPTION_ITEMS.len();
+                self.highlight.restart();
+                return MenuAction::None;
+            }
+            MenuInput::Down => {
+                self.selected = (self.selected + 1) % OPTION_ITEMS.len();
+                self.highlight.restart();
+                return MenuAction::None;
+            }
+            MenuInput::Back => return MenuAction::Back,
+            MenuInput::Confirm if OPTION_ITEMS[self.selected] == OptionItem::Back => return MenuAction::Back,
+            MenuInput::Left => -1,
+            MenuInput::Right | MenuInput::Confirm => 1,
+            MenuInput::None => return MenuAction::None,
+        };
+
+        match OPTION_ITEMS[self.selected] {
+            OptionItem::Theme => {
+                let current = theme_names.iter().position(|name| *name == settings.theme.name).unwrap_or(0);
+                settings.theme.name = cycle(theme_names, current, step).clone();
+            }
+            OptionItem::SoundTheme => {
+                let current = SOUND_THEMES.iter().position(|theme| *theme == settings.audio.sound_theme).unwrap_or(0);
+                settings.audio.sound_theme = *cycle(&SOUND_THEMES, current, step);
+            }
+            OptionItem::Trail => {
+                let current = TRAIL_STYLES.iter().position(|style| *style == settings.trail.style).unwrap_or(0);
+                settings.trail.style = *cycle(&TRAIL_STYLES, current, step);
+            }
+            OptionItem::Countdown => {
+                let countdown = settings.serve.countdown as i32 + step;
+                settings.serve.countdown = countdown.clamp(0, MAX_COUNTDOWN as i32) as u32;
+            }
+            OptionItem::ShowCountdown => settings.serve.show_countdown = !settings.serve.show_countdown,
+            OptionItem::InstantReplay => settings.instant_replay.enabled = !settings.instant_replay.enabled,
+            OptionItem::Particles => settings.particles.enabled = !settings.particles.enabled,
+            OptionItem::Effects => settings.effects.enabled = !settings.effects.enabled,
+            OptionItem::Shake => slide(&mut settings.effects.shake, step),
+            OptionItem::HitStop => slide(&mut settings.effects.hit_stop, step),
+            OptionItem::Zoom => slide(&mut settings.effects.zoom, step),
+            OptionItem::PostProcess => settings.post_process.enabled = !settings.post_process.enabled,
+            OptionItem::Back => return MenuAction::None,
+        }
+        self.highlight.restart();
+        MenuAction::SettingsChanged
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, theme: &Theme, settings: &Settings) {
+        let on_off = |value: bool| if value { "ON" } else { "OFF" }.to_string();
+        let percent = |value: f32| format!("{:.0}%", value * 100.0);
+
+        let items: Vec<String> = OPTION_ITEMS.iter().map(|item| match item {
+            OptionItem::Theme => format!("THEME  < {} >", settings.theme.name),
+            OptionItem::SoundTheme => format!("SOUNDS  < {:?} >", settings.audio.sound_theme),
+            OptionItem::Trail => format!("BALL TRAIL  < {:?} >", settings.trail.style),
+            OptionItem::Countdown => format!("SERVE COUNTDOWN  < {}s >", settings.serve.countdown),
+            OptionItem::ShowCountdown => format!("SHOW COUNTDOWN  {}", on_off(settings.serve.show_countdown)),
+            OptionItem::InstantReplay => format!("INSTANT REPLAY  {}", on_off(settings.instant_replay.enabled)),
+            OptionItem::Particles => format!("PARTICLES  {}", on_off(settings.particles.enabled)),
+            OptionItem::Effects => format!("CAMERA EFFECTS  {}", on_off(settings.effects.enabled)),
+            OptionItem::Shake => format!("SCREEN SHAKE  < {} >", percent(settings.effects.shake)),
+            OptionItem::HitStop => format!("HIT-STOP  < {} >", percent(settings.effects.hit_stop)),
+            OptionItem::Zoom => format!("ZOOM PUNCH  < {} >", percent(settings.effects.zoom)),
+            OptionItem::PostProcess => format!("CRT FILTER  {}", on_off(settings.post_process.enabled)),
+            OptionItem::Back => String::from("BACK"),
+        }).collect();
+
+        draw_menu(d, theme, "OPTIONS", &items, self.selected, self.highlight.value("scale"));
+    }
+}
+
+impl Scene for OptionsMenu {
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand {
+        let previous = ctx.settings.clone();
+        let theme_names = ctx.theme_manager.names();
+        match self.navigate(rl, ctx.settings, &theme_names) {
+            MenuAction::SettingsChanged => {
+                apply_settings(ctx, &previous);
+                SceneCommand::None
+            }
+            MenuAction::Back => SceneCommand::Pop(Transition::Fade),
+            _ => SceneCommand::None,
+        }
+    }
+
+    fn exit(&mut self, ctx: &mut Context) {
+        if let Err(err) = ctx.settings.save(settings::SETTINGS_PATH) {
+            eprintln!("failed to save {}: {}", settings::SETTINGS_PATH, err);
+        }
+    }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        OptionsMenu::draw(self, d, &ctx.game.theme, ctx.settings);
+    }
+}
+
+// hands the changed options to every system that uses them
+fn apply_settings(ctx: &mut Context, previous: &Settings) {
+    let settings = &*ctx.settings;
+    ctx.game.trail = settings.trail.clone();
+    // the countdown is part of the simulation, a running match and its replay keep theirs until the next match
+    ctx.game.serve.show_countdown = settings.serve.show_countdown;
+    ctx.game.instant_replay = settings.instant_replay.clone();
+    ctx.game.particles.set_settings(&settings.particles);
+    ctx.camera_effects.settings = settings.effects.clone();
+    if settings.theme.name != previous.theme.name {
+        ctx.game.theme = ctx.theme_manager.select(&settings.theme.name).clone();
+    }
+    if settings.audio.sound_theme != previous.audio.sound_theme {
+        *ctx.audio_manager = audiosystem::SoundManager::new(Some(audiosystem::load_theme(settings.audio.sound_theme)),
+                                                           &settings.audio);
+    }
+    if settings.post_process.enabled != previous.post_process.enabled {
+        *ctx.post_process = PostProcess::new(&settings.post_process);
+    }
+}
+
+fn cycle<T>(values: &[T], current: usize, step: i32) -> &T {
+    let len = values.len() as i32;
+    &values[((current as i32 + step) % len + len) as usize % values.len()]
+}
+
+fn slide(value: &mut f32, step: i32) {
+    *value = ((*value + step as f32 * SLIDER_STEP) * 10.0).round() / 10.0;
+    *value = value.clamp(0.0, 1.0);
+}
+
+// the selected entry pops up a bit when the selection or its value changes
+pub fn highlight_timeline() -> Timeline {
+    Timeline::new().then("scale", 1.25, 1.0, 0.2, Ease::OutBack)
+}
+
+/// Draws a title and a centered list with the selected entry highlighted and scaled by `highlight`.
+pub fn draw_menu(d: &mut RaylibDrawHandle, theme: &Theme, title: &str, items: &[String], selected: usize, highlight: f32) {
+    let screen_width = d.get_screen_width();
+    let screen_height = d.get_screen_height();
+
+    let mut y = screen_height / 2 - (items.len() as i32 * 60 + 160) / 2;
+    let title_width = measure_text(title, 100);
+    d.draw_text(title, screen_width / 2 - title_width / 2, y, 100, theme.colors.text);
+    y += 160;
+
+    for (i, item) in items.iter().enumerate() {
+        let color = if i == selected { theme.colors.accent } else { theme.colors.text };
+        let text = if i == selected { format!("> {} <", item) } else { item.clone() };
+        let size = if i == selected { (40.0 * highlight) as i32 } else { 40 };
+        let width = measure_text(&text, size);
+        d.draw_text(&text, screen_width / 2 - width / 2, y + 20 - size / 2, size, color);
+        y += 60;
+    }
+}
@@ -0,0 +1,148 @@
+use raylib::ffi::{Music, FileExists, LoadMusicStream, UnloadMusicStream, PlayMusicStream, StopMusicStream, UpdateMusicStream, IsMusicPlaying, SetMusicVolume};
+use crate::const_c;
+use crate::gamestate::GameState;
+use crate::settings::MusicSettings;
+
+// how fast the music ducks under a sound effect and comes back, in volume per second
+const DUCK_ATTACK: f32 = 20.0;
+const DUCK_RELEASE: f32 = 2.0;
+
+struct Track {
+    music: Music,
+    // 0.0 silent to 1.0 full volume, driven by the crossfade
+    fade: f32,
+}
+
+/// Streams the playlist of the current game state and crossfades between tracks.
+pub struct MusicPlayer {
+    settings: MusicSettings,
+    current: Option<Track>,
+    fading_out: Vec<Track>,
+    playlist: Vec<String>,
+    playlist_index: usize,
+    state: Option<GameState>,
+    duck: f32,
+    duck_timer: f32,
+}
+
+impl MusicPlayer {
+    pub fn new(settings: MusicSettings) -> MusicPlayer {
+        MusicPlayer {
+            settings,
+            current: None,
+            fading_out: Vec::new(),
+            playlist: Vec::new(),
+            playlist_index: 0,
+            state: None,
+            duck: 1.0,
+            duck_timer: 0.0,
+        }
+    }
+
+    /// Switches to the playlist of `state`, keeps the current track if the playlist did not change.
+    pub fn set_state(&mut self, state: GameState) {
+        if self.state == Some(state) {
+            return;
+        }
+        self.state = Some(state);
+
+        let playlist = self.settings.playlist(state).clone();
+        if playlist == self.playlist && self.current.is_some() {
+            return;
+        }
+
+        self.playlist = playlist;
+        self.playlist_index = 0;
+        self.play_track(0);
+    }
+
+    /// Lowers the music for a moment so a sound effect can be heard.
+    pub fn duck(&mut self) {
+        self.duck_timer = self.settings.duck_time;
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        let fade_step = if self.settings.crossfade > 0.0 { delta_time / self.settings.crossfade } else { 1.0 };
+
+        // move the duck level towards its target
+        self.duck_timer -= delta_time;
+        if self.duck_timer > 0.0 {
+            self.duck = (self.duck - DUCK_ATTACK * delta_time).max(self.settings.duck_volume);
+        } else {
+            self.duck = (self.duck + DUCK_RELEASE * delta_time).min(1.0);
+        }
+        let volume = self.settings.volume * self.duck;
+
+        // fade out and unload previous tracks
+        for track in self.fading_out.iter_mut() {
+            track.fade -= fade_step;
+            unsafe {
+                UpdateMusicStream(track.music);
+                SetMusicVolume(track.music, track.fade.max(0.0) * volume);
+            }
+        }
+        self.fading_out.retain(|track| {
+            if track.fade > 0.0 {
+                return true;
+            }
+            unsafe {
+                StopMusicStream(track.music);
+                UnloadMusicStream(track.music);
+            }
+            false
+        });
+
+        let mut finished = false;
+        if let Some(track) = self.current.as_mut() {
+            track.fade = (track.fade + fade_step).min(1.0);
+            unsafe {
+                UpdateMusicStream(track.music);
+                SetMusicVolume(track.music, track.fade * volume);
+                finished = !IsMusicPlaying(track.music);
+            }
+        }
+
+        // a single track loops by itself, longer playlists advance when a track ends
+        if finished {
+            let next = self.playlist_index + 1;
+            if next < self.playlist.len() {
+                self.play_track(next);
+            } else if self.settings.looping {
+                self.play_track(0);
+            } else if let Some(track) = self.current.take() {
+                self.fading_out.push(track);
+            }
+        }
+    }
+
+    /// Crossfades to the track at `index`, skipping files that are missing or fail to load.
+    fn play_track(&mut self, index: usize) {
+        if let Some(track) = self.current.take() {
+            self.fading_out.push(track);
+        }
+
+        for offset in 0..self.playlist.len() {
+            let index = (index + offset) % self.playlist.len();
+            let path = &self.playlist[index];
+
+            let mut music = unsafe {
+                if !FileExists(const_c!(path.as_str())) {
+                    continue;
+                }
+                LoadMusicStream(const_c!(path.as_str()))
+            };
+            if music.ctxData.is_null() {
+                continue;
+            }
+
+            music.looping = self.settings.looping && self.playlist.len() == 1;
+            unsafe {
+                SetMusicVolume(music, 0.0);
+                PlayMusicStream(music);
+            }
+            self.current = Some(Track { music, fade: 0.0 });
+            self.playlist_index = index;
+            return;
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use std::io::{self, Cursor, Read};
+use std::net::{SocketAddr, UdpSocket};
+use raylib::prelude::Vector2;
+use crate::events::{GameEvent, Side};
+use crate::game::MatchState;
+use crate::input::PaddleInput;
+use crate::netsim::SimulatedTransport;
+use crate::settings::Netcode;
+
+const MAGIC: &[u8; 4] = b"P50N";
+/// Peers with a different protocol version can't play together.
+pub const PROTOCOL_VERSION: u16 = 5;
+// more than any message we send, less than the usual MTU
+const MAX_PACKET: usize = 1400;
+// inputs a client repeats in every packet so a lost packet doesn't lose an input
+pub const MAX_INPUTS: usize = 32;
+
+/// Everything peers of a networked match say to each other.
+#[derive(Clone, Debug)]
+pub enum Message {
+    /// client asks the host for a seat
+    Join { version: u16 },
+    /// spectator asks to follow the match, repeated while it watches so the host keeps sending
+    Watch { version: u16 },
+    /// host accepted the client, who plays the right paddle in an arena of `arena` size,
+    /// `seed` and `countdown` let a rollback client simulate the same match
+    Welcome { arena: (i32, i32), netcode: Netcode, seed: u64, countdown: u32 },
+    Refused { reason: String },
+    /// paddle inputs of the ticks starting at `first_tick`, `ack` is the last tick of the
+    /// receiver's inputs the sender has
+    Inputs { first_tick: u32, ack: u32, inputs: Vec<PaddleInput> },
+    /// checksum of the state at the start of `tick` in a rollback match
+    Checksum { tick: u32, checksum: u64 },
+    /// a host announcing its game on the LAN, `port` is where it takes players and `id` tells
+    /// copies of the same beacon apart from other games
+    Beacon { version: u16, id: u32, name: String, port: u16, players: u8, capacity: u8 },
+    /// host simulation after `tick`, `ack` is the last client input tick it used
+    State { tick: u32, ack: u32, state: MatchState, events: Vec<GameEvent> },
+    Leave,
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(64);
+        buffer.extend_from_slice(MAGIC);
+        match self {
+            Message::Join { version } => {
+                buffer.push(0);
+                buffer.extend_from_slice(&version.to_le_bytes());
+            }
+            Message::Welcome { arena, netcode, seed, countdown } => {
+                buffer.push(1);
+                buffer.extend_from_slice(&arena.0.to_le_bytes());
+                buffer.extend_from_slice(&arena.1.to_le_bytes());
+                buffer.push(match netcode {
+                    Netcode::Host => 0,
+                    Netcode::Rollback => 1,
+                });
+                buffer.extend_from_slice(&seed.to_le_bytes());
+                buffer.extend_from_slice(&countdown.to_le_bytes());
+            }
+            Message::Refused { reason } => {
+                buffer.push(2);
+                write_string(&mut buffer, reason);
+            }
+            Message::Inputs { first_tick, ack, inputs } => {
+                buffer.push(3);
+                buffer.extend_from_slice(&first_tick.to_le_bytes());
+                buffer.extend_from_slice(&ack.to_le_bytes());
+                buffer.push(inputs.len().min(MAX_INPUTS) as u8);
+                for input in inputs.iter().take(MAX_INPUTS) {
+                    buffer.push(input.direction.round().clamp(-1.0, 1.0) as i8 as u8);
+                }
+            }
+            Message::State { tick, ack, state, events } => {
+                buffer.push(4);
+                buffer.extend_from_slice(&tick.to_le_bytes());
+                buffer.extend_from_slice(&ack.to_le_bytes());
+                write_state(&mut buffer, state);
+                buffer.push(events.len().min(255) as u8);
+                for event in events.iter().take(255) {
+                    write_event(&mut buffer, event);
+                }
+            }
+            Message::Leave => buffer.push(5),
+            Message::Checksum { tick, checksum } => {
+                buffer.push(6);
+                buffer.extend_from_slice(&tick.to_le_bytes());
+                buffer.extend_from_slice(&checksum.to_le_bytes());
+            }
+            // the version comes first so games of other versions can still be listed
+            Message::Beacon { version, id, name, port, players, capacity } => {
+                buffer.push(7);
+                buffer.extend_from_slice(&version.to_le_bytes());
+                buffer.extend_from_slice(&id.to_le_bytes());
+                buffer.extend_from_slice(&port.to_le_bytes());
+                buffer.extend_from_slice(&[*players, *capacity]);
+                write_string(&mut buffer, name);
+            }
+            Message::Watch { version } => {
+                buffer.push(8);
+                buffer.extend_from_slice(&version.to_le_bytes());
+            }
+        }
+        buffer
+    }
+
+    pub fn decode(bytes: &[u8]) -> io::Result<Message> {
+        let mut reader = Cursor::new(bytes);
+        if &read_bytes::<4>(&mut reader)? != MAGIC {
+            return Err(invalid("not a pong50th packet"));
+        }
+
+        Ok(match read_u8(&mut reader)? {
+            0 => Message::Join { version: u16::from_le_bytes(read_bytes(&mut reader)?) },
+            1 => Message::Welcome {
+                arena: (read_i32(&mut reader)?, read_i32(&mut reader)?),
+                netcode: if read_u8(&mut reader)? == 0 { Netcode::Host } else { Netcode::Rollback },
+                seed: u64::from_le_bytes(read_bytes(&mut reader)?),
+                countdown: read_u32(&mut reader)?,
+            },
+            2 => Message::Refused { reason: read_string(&mut reader)? },
+            3 => {
+                let first_tick = read_u32(&mut reader)?;
+                let ack = read_u32(&mut reader)?;
+                let count = read_u8(&mut reader)? as usize;
+                let mut inputs = Vec::with_capacity(count);
+                for _ in 0..count {
+                    // a paddle moves one step a tick at most, anything else is a broken or crafted packet
+                    let direction = read_u8(&mut reader)? as i8;
+                    if !(-1..=1).contains(&direction) {
+                        return Err(invalid(&format!("invalid direction {}", direction)));
+                    }
+                    inputs.push(PaddleInput { direction: direction as f32 });
+                }
+                Message::Inputs { first_tick, ack, inputs }
+            }
+            4 => {
+                let tick = read_u32(&mut reader)?;
+                let ack = read_u32(&mut reader)?;
+                let state = read_state(&mut reader)?;
+                let count = read_u8(&mut reader)? as usize;
+                let mut events = Vec::with_capacity(count);
+                for _ in 0..count {
+                    events.push(read_event(&mut reader)?);
+                }
+                Message::State { tick, ack, state, events }
+            }
+            5 => Message::Leave,
+            6 => Message::Checksum {
+                tick: read_u32(&mut reader)?,
+                checksum: u64::from_le_bytes(read_bytes(&mut reader)?),
+            },
+            7 => Message::Beacon {
+                version: u16::from_le_bytes(read_bytes(&mut reader)?),
+                id: read_u32(&mut reader)?,
+                port: u16::from_le_bytes(read_bytes(&mut reader)?),
+                players: read_u8(&mut reader)?,
+                capacity: read_u8(&mut reader)?,
+                name: read_string(&mut reader)?,
+            },
+            8 => Message::Watch { version: u16::from_le_bytes(read_bytes(&mut reader)?) },
+            kind => return Err(invalid(&format!("unknown message {}", kind))),
+        })
+    }
+}
+
+/// Moves raw packets between peers, a plain socket or something standing in for one.
+pub trait Transport {
+    /// Sends without waiting, a packet that can't be sent is lost like any other.
+    fn send_to(&self, bytes: &[u8], to: SocketAddr);
+    /// The next packet that arrived, None once nothing is left.
+    fn receive_from(&self, buffer: &mut [u8]) -> Option<(usize, SocketAddr)>;
+}
+
+/// A non-blocking UDP socket.
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+
+impl Transport for UdpTransport {
+    fn send_to(&self, bytes: &[u8], to: SocketAddr) {
+        // a lost packet is no different from a dropped one, the protocol repeats what matters
+        let _ = self.socket.send_to(bytes, to);
+    }
+
+    fn receive_from(&self, buffer: &mut [u8]) -> Option<(usize, SocketAddr)> {
+        loop {
+            match self.socket.recv_from(buffer) {
+                Ok(received) => return Some(received),
+                // windows reports an unreachable peer on the next receive, that's not fatal here
+                Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// Sends and receives whole messages, through the network simulator when it is turned on.
+pub struct Connection {
+    transport: SimulatedTransport<UdpTransport>,
+}
+
+impl Connection {
+    /// Binds to `port` on every interface, 0 picks a free port.
+    pub fn bind(port: u16) -> io::Result<Connection> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Connection { transport: SimulatedTransport::new(UdpTransport { socket }) })
+    }
+
+    /// Allows sending to broadcast addresses.
+    pub fn enable_broadcast(&self) -> io::Result<()> {
+        self.transport.inner().socket.set_broadcast(true)
+    }
+
+    pub fn send(&self, message: &Message, to: SocketAddr) {
+        self.transport.send_to(&message.encode(), to);
+    }
+
+    /// The next valid message that arrived, None once nothing is left.
+    pub fn receive(&self) -> Option<(Message, SocketAddr)> {
+        let mut buffer = [0; MAX_PACKET];
+        while let Some((length, from)) = self.transport.receive_from(&mut buffer) {
+            if let Ok(message) = Message::decode(&buffer[..length]) {
+                return Some((message, from));
+            }
+        }
+        None
+    }
+}
+
+// at most 255 bytes, cut at a character boundary
+fn write_string(buffer: &mut Vec<u8>, text: &str) {
+    let mut length = text.len().min(255);
+    while !text.is_char_boundary(length) {
+        length -= 1;
+    }
+    buffer.push(length as u8);
+    buffer.extend_from_slice(&text.as_bytes()[..length]);
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let mut bytes = vec![0; read_u8(reader)? as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+fn write_vector(buffer: &mut Vec<u8>, vector: Vector2) {
+    buffer.extend_from_slice(&vector.x.to_le_bytes());
+    buffer.extend_from_slice(&vector.y.to_le_bytes());
+}
+
+fn write_state(buffer: &mut Vec<u8>, state: &MatchState) {
+    write_vector(buffer, state.ball_position);
+    write_vector(buffer, state.ball_velocity);
+    buffer.extend_from_slice(&state.left_paddle.to_le_bytes());
+    buffer.extend_from_slice(&state.right_paddle.to_le_bytes());
+    buffer.extend_from_slice(&state.score.0.to_le_bytes());
+    buffer.extend_from_slice(&state.score.1.to_le_bytes());
+    buffer.extend_from_slice(&state.serve_timer.to_le_bytes());
+    buffer.extend_from_slice(&state.go_timer.to_le_bytes());
+    buffer.push(state.paused as u8 | (state.match_over as u8) << 1);
+}
+
+fn read_state(reader: &mut impl Read) -> io::Result<MatchState> {
+    let ball_position = read_vector(reader)?;
+    let ball_velocity = read_vector(reader)?;
+    let left_paddle = read_f32(reader)?;
+    let right_paddle = read_f32(reader)?;
+    let score = (read_i32(reader)?, read_i32(reader)?);
+    let serve_timer = read_f32(reader)?;
+    let go_timer = read_f32(reader)?;
+    let flags = read_u8(reader)?;
+    Ok(MatchState {
+        ball_position,
+        ball_velocity,
+        left_paddle,
+        right_paddle,
+        score,
+        serve_timer,
+        go_timer,
+        paused: flags & 1 != 0,
+        match_over: flags & 2 != 0,
+    })
+}
+
+// NO_SIDE stands for none where a side is optional
+const NO_SIDE: u8 = 255;
+
+fn side_byte(side: Side) -> u8 {
+    side.index() as u8
+}
+
+fn read_side(reader: &mut impl Read) -> io::Result<Side> {
+    read_optional_side(reader)?.ok_or_else(|| invalid("missing side"))
+}
+
+fn read_optional_side(reader: &mut impl Read) -> io::Result<Option<Side>> {
+    match read_u8(reader)? {
+        NO_SIDE => Ok(None),
+        byte => Side::ALL.get(byte as usize).copied().map(Some).ok_or_else(|| invalid(&format!("unknown side {}", byte))),
+    }
+}
+
+fn write_score(buffer: &mut Vec<u8>, score: &[i32; 4]) {
+    for points in score {
+        buffer.extend_from_slice(&points.to_le_bytes());
+    }
+}
+
+fn read_score(reader: &mut impl Read) -> io::Result<[i32; 4]> {
+    Ok([read_i32(reader)?, read_i32(reader)?, read_i32(reader)?, read_i32(reader)?])
+}
+
+fn write_event(buffer: &mut Vec<u8>, event: &GameEvent) {
+    match *event {
+        GameEvent::PaddleHit { side, position, offset, speed } => {
+            buffer.extend_from_slice(&[0, side_byte(side)]);
+            write_vector(buffer, position);
+            buffer.extend_from_slice(&offset.to_le_bytes());
+            buffer.extend_from_slice(&speed.to_le_bytes());
+        }
+        GameEvent::WallBounce { side, position, velocity } => {
+            buffer.extend_from_slice(&[1, side_byte(side)]);
+            write_vector(buffer, position);
+            write_vector(buffer, velocity);
+        }
+        GameEvent::Scored { side, conceded, position, score } => {
+            buffer.extend_from_slice(&[2, side.map_or(NO_SIDE, side_byte), side_byte(conceded)]);
+            write_vector(buffer, position);
+            write_score(buffer, &score);
+        }
+        GameEvent::Countdown { number } => {
+            buffer.push(3);
+            buffer.extend_from_slice(&number.to_le_bytes());
+        }
+        GameEvent::Serve { position, direction } => {
+            buffer.push(4);
+            write_vector(buffer, position);
+            write_vector(buffer, direction);
+        }
+        GameEvent::MatchWon { side, score } => {
+            buffer.extend_from_slice(&[5, side_byte(side)]);
+            write_score(buffer, &score);
+        }
+    }
+}
+
+fn read_event(reader: &mut impl Read) -> io::Result<GameEvent> {
+    Ok(match read_u8(reader)? {
+        0 => GameEvent::PaddleHit {
+            side: read_side(reader)?,
+            position: read_vector(reader)?,
+            offset: read_f32(reader)?,
+            speed: read_f32(reader)?,
+        },
+        1 => GameEvent::WallBounce { side: read_side(reader)?, position: read_vector(reader)?, velocity: read_vector(reader)? },
+        2 => GameEvent::Scored {
+            side: read_optional_side(reader)?,
+            conceded: read_side(reader)?,
+            position: read_vector(reader)?,
+            score: read_score(reader)?,
+        },
+        3 => GameEvent::Countdown { number: read_u32(reader)? },
+        4 => GameEvent::Serve { position: read_vector(reader)?, direction: read_vector(reader)? },
+        5 => GameEvent::MatchWon { side: read_side(reader)?, score: read_score(reader)? },
+        kind => return Err(invalid(&format!("unknown event {}", kind))),
+    })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    Ok(read_bytes::<1>(reader)?[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_i32(reader: &mut impl Read) -> io::Result<i32> {
+    Ok(i32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    Ok(f32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_vector(reader: &mut impl Read) -> io::Result<Vector2> {
+    Ok(Vector2::new(read_f32(reader)?, read_f32(reader)?))
+}
@@ -0,0 +1,765 @@
+use std::collections::VecDeque;
+use std::net::{SocketAddr, ToSocketAddrs};
+use raylib::prelude::*;
+use crate::events::{self, emit, Side};
+use crate::discovery::{Announcer, Browser, DiscoveredGame};
+use crate::game::{is_valid_arena, Game, GameMode, MatchState, TICK};
+use crate::gameover;
+use crate::gamestate::GameState;
+use crate::input::{any_pressed, poll_menu_input, poll_player_input, MenuInput, PaddleInput};
+use crate::menu::{draw_menu, highlight_timeline};
+use crate::net::{Connection, Message, MAX_INPUTS, PROTOCOL_VERSION};
+use crate::rollback::RollbackSession;
+use crate::scene::{Context, Scene, SceneCommand, Transition};
+use crate::settings::{self, NetSettings, Netcode};
+use crate::spectate::{SpectatorSession, Spectators};
+use crate::time::{self, DELTA_TIME};
+use crate::tween::Timeline;
+
+// seconds between join requests while waiting for the host to answer
+const JOIN_INTERVAL: f32 = 0.5;
+// seconds between tries to listen for beacons, another copy of the game may hold the port
+const BROWSE_INTERVAL: f32 = 1.0;
+// seconds the remote paddle and the ball are drawn behind the newest host state,
+// enough that there are two states to blend between most of the time
+const INTERPOLATION_DELAY: f64 = 0.1;
+// client inputs the host holds at most, more means the client clock runs ahead
+const MAX_QUEUED_INPUTS: usize = 16;
+// client inputs kept for prediction while the host doesn't answer
+const MAX_PENDING_INPUTS: usize = 256;
+// seconds of ticks a client runs at most in one frame
+const MAX_CATCH_UP: f32 = 0.1;
+
+/// The authoritative side of a networked match, simulates everything and plays the left paddle.
+pub struct HostSession {
+    connection: Connection,
+    client: SocketAddr,
+    // sent again when the client didn't get it
+    welcome: Message,
+    spectators: Spectators,
+    // client inputs not simulated yet, by client tick
+    inputs: VecDeque<(u32, PaddleInput)>,
+    last_queued: u32,
+    // repeated while no new client input arrived
+    last_input: PaddleInput,
+    ack: u32,
+    tick: u32,
+    silence: f32,
+}
+
+impl HostSession {
+    pub fn new(connection: Connection, client: SocketAddr, welcome: Message) -> HostSession {
+        HostSession {
+            connection,
+            client,
+            spectators: Spectators::new(welcome.clone()),
+            welcome,
+            inputs: VecDeque::new(),
+            last_queued: 0,
+            last_input: PaddleInput::default(),
+            ack: 0,
+            tick: 0,
+            silence: 0.0,
+        }
+    }
+
+    /// Runs the match for this frame and sends the result to the client, Err once the client is gone.
+    pub fn update(&mut self, game: &mut Game, input: PaddleInput, settings: &NetSettings) -> Result<(), String> {
+        self.silence += unsafe { DELTA_TIME };
+        while let Some((message, from)) = self.connection.receive() {
+            if from != self.client {
+                self.spectators.receive(&self.connection, message, from, settings);
+                continue;
+            }
+            self.silence = 0.0;
+
+            match message {
+                // the welcome got lost
+                Message::Join { .. } => self.connection.send(&self.welcome, from),
+                Message::Inputs { first_tick, inputs, .. } => self.queue_inputs(first_tick, &inputs),
+                Message::Leave => return Err(String::from("the other player left")),
+                _ => {}
+            }
+        }
+        if self.silence > settings.timeout {
+            return Err(String::from("connection lost"));
+        }
+
+        let ticks = game.update_versus(input, || {
+            if let Some((tick, input)) = self.inputs.pop_front() {
+                self.ack = tick;
+                self.last_input = input;
+            }
+            self.last_input
+        });
+        self.tick += ticks;
+
+        // events stay queued for this machine, the client gets copies to play them too
+        self.connection.send(&Message::State {
+            tick: self.tick,
+            ack: self.ack,
+            state: game.match_state(),
+            events: events::queued(),
+        }, self.client);
+        self.spectators.update(&self.connection, self.tick, game.match_state(), events::queued(), settings);
+        Ok(())
+    }
+
+    fn queue_inputs(&mut self, first_tick: u32, inputs: &[PaddleInput]) {
+        for (i, input) in inputs.iter().enumerate() {
+            // past the last tick only comes from a broken packet
+            let tick = match first_tick.checked_add(i as u32) {
+                Some(tick) => tick,
+                None => return,
+            };
+            if tick > self.last_queued {
+                self.inputs.push_back((tick, *input));
+                self.last_queued = tick;
+            }
+        }
+        while self.inputs.len() > MAX_QUEUED_INPUTS {
+            self.inputs.pop_front();
+        }
+    }
+
+    pub fn leave(&self) {
+        self.connection.send(&Message::Leave, self.client);
+        self.spectators.leave(&self.connection);
+    }
+}
+
+/// The other side of a networked match, sends paddle inputs and shows what the host simulated.
+///
+/// The own paddle is predicted from the inputs the host hasn't used yet, the ball and the
+/// host paddle are blended between the last host states.
+pub struct ClientSession {
+    connection: Connection,
+    host: SocketAddr,
+    tick: u32,
+    accumulator: f32,
+    // own inputs the host hasn't acknowledged yet
+    pending: VecDeque<(u32, PaddleInput)>,
+    // host states and the local time they arrived, oldest first
+    states: VecDeque<(f64, MatchState)>,
+    latest_tick: u32,
+    predicted_paddle: Option<f32>,
+    silence: f32,
+}
+
+impl ClientSession {
+    pub fn new(connection: Connection, host: SocketAddr) -> ClientSession {
+        ClientSession {
+            connection,
+            host,
+            tick: 0,
+            accumulator: 0.0,
+            pending: VecDeque::new(),
+            states: VecDeque::new(),
+            latest_tick: 0,
+            predicted_paddle: None,
+            silence: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, game: &mut Game, input: PaddleInput, settings: &NetSettings) -> Result<(), String> {
+        let now = unsafe { time::TIME };
+        self.silence += unsafe { DELTA_TIME };
+        while let Some((message, from)) = self.connection.receive() {
+            if from != self.host {
+                continue;
+            }
+            self.silence = 0.0;
+
+            match message {
+                Message::State { tick, ack, state, events } => {
+                    // late packets are older than what is already shown
+                    if tick <= self.latest_tick && self.latest_tick > 0 {
+                        continue;
+                    }
+                    self.latest_tick = tick;
+                    self.states.push_back((now, state));
+                    for event in events {
+                        emit(event);
+                    }
+                    self.reconcile(game, ack, state.right_paddle);
+                }
+                Message::Leave => return Err(String::from("the host left")),
+                _ => {}
+            }
+        }
+        if self.silence > settings.timeout {
+            return Err(String::from("connection lost"));
+        }
+
+        // the own paddle moves right away, the host catches up once the inputs arrive
+        self.accumulator = (self.accumulator + unsafe { DELTA_TIME }).min(MAX_CATCH_UP);
+        while self.accumulator >= TICK {
+            self.accumulator -= TICK;
+            self.tick += 1;
+            self.pending.push_back((self.tick, input));
+            if self.pending.len() > MAX_PENDING_INPUTS {
+                self.pending.pop_front();
+            }
+            self.predicted_paddle = self.predicted_paddle.map(|y| game.step_paddle(y, input));
+        }
+
+        let unacked: Vec<PaddleInput> = self.pending.iter().rev().take(MAX_INPUTS).rev().map(|(_, input)| *input).collect();
+        let first_tick = self.tick + 1 - unacked.len() as u32;
+        self.connection.send(&Message::Inputs { first_tick, ack: 0, inputs: unacked }, self.host);
+
+        if let Some(mut state) = self.interpolated(now, game.screen_width as f32) {
+            if let Some(paddle) = self.predicted_paddle {
+                state.right_paddle = paddle;
+            }
+            game.apply_match_state(&state);
+        }
+        Ok(())
+    }
+
+    // starts over from the paddle position the host confirmed and applies the inputs it hasn't seen
+    fn reconcile(&mut self, game: &Game, ack: u32, confirmed: f32) {
+        while self.pending.front().map_or(false, |(tick, _)| *tick <= ack) {
+            self.pending.pop_front();
+        }
+        let mut y = confirmed;
+        for (_, input) in self.pending.iter() {
+            y = game.step_paddle(y, *input);
+        }
+        self.predicted_paddle = Some(y);
+    }
+
+    fn interpolated(&mut self, now: f64, arena_width: f32) -> Option<MatchState> {
+        let render_time = now - INTERPOLATION_DELAY;
+        // keep one state from before the render time to blend from
+        while self.states.len() > 2 && self.states[1].0 <= render_time {
+            self.states.pop_front();
+        }
+
+        let (from_time, from) = *self.states.front()?;
+        let (to_time, to) = match self.states.get(1) {
+            Some(next) => *next,
+            None => return Some(from),
+        };
+        if render_time <= from_time {
+            return Some(from);
+        }
+
+        let amount = ((render_time - from_time) / (to_time - from_time).max(1e-6)).min(1.0) as f32;
+        let mut state = to;
+        // don't sweep the ball across the arena when it was put back in the center
+        if from.ball_position.distance_to(to.ball_position) < arena_width / 4.0 {
+            state.ball_position = from.ball_position.lerp(to.ball_position, amount);
+        }
+        state.left_paddle = from.left_paddle + (to.left_paddle - from.left_paddle) * amount;
+        Some(state)
+    }
+
+    pub fn leave(&self) {
+        self.connection.send(&Message::Leave, self.host);
+    }
+}
+
+pub enum NetSession {
+    Host(HostSession),
+    Client(ClientSession),
+    Rollback(RollbackSession),
+    Spectator(SpectatorSession),
+}
+
+impl NetSession {
+    pub fn update(&mut self, game: &mut Game, input: PaddleInput, settings: &NetSettings) -> Result<(), String> {
+        match self {
+            NetSession::Host(host) => host.update(game, input, settings),
+            NetSession::Client(client) => client.update(game, input, settings),
+            NetSession::Rollback(peer) => peer.update(game, input, settings),
+            NetSession::Spectator(spectator) => spectator.update(game, settings),
+        }
+    }
+
+    /// Only an authoritative host can start a rematch, rollback matches are played once.
+    pub fn is_host(&self) -> bool {
+        matches!(self, NetSession::Host(_))
+    }
+
+    /// None for spectators.
+    pub fn local_side(&self) -> Option<Side> {
+        match self {
+            NetSession::Host(_) => Some(Side::Left),
+            NetSession::Client(_) => Some(Side::Right),
+            NetSession::Rollback(peer) => Some(peer.local_side()),
+            NetSession::Spectator(_) => None,
+        }
+    }
+
+    /// How many people watch the match, only the host knows.
+    pub fn spectators(&self) -> usize {
+        match self {
+            NetSession::Host(host) => host.spectators.count(),
+            NetSession::Rollback(peer) => peer.spectators(),
+            _ => 0,
+        }
+    }
+
+    pub fn desync(&self) -> Option<u32> {
+        match self {
+            NetSession::Rollback(peer) => peer.desync(),
+            _ => None,
+        }
+    }
+
+    pub fn leave(&self) {
+        match self {
+            NetSession::Host(host) => host.leave(),
+            NetSession::Client(client) => client.leave(),
+            NetSession::Rollback(peer) => peer.leave(),
+            NetSession::Spectator(spectator) => spectator.leave(),
+        }
+    }
+}
+
+/// What the lobby does as soon as it opens, set from the command line.
+#[derive(Clone, Debug)]
+pub enum LobbyAction {
+    Host,
+    Join(String),
+    Watch(String),
+}
+
+// a connection being set up in the lobby
+enum Pending {
+    Hosting { connection: Connection, announcer: Option<Announcer> },
+    // `spectate` asks to watch instead of play
+    Joining { connection: Connection, host: SocketAddr, spectate: bool, timer: f32, elapsed: f32 },
+}
+
+// the games found on the LAN are listed between WATCH and BACK
+const HOST_ITEM: usize = 0;
+const JOIN_ITEM: usize = 1;
+const WATCH_ITEM: usize = 2;
+const FIRST_GAME_ITEM: usize = 3;
+
+/// Hosting a game or joining one by address or from the games announced on the LAN,
+/// typing while JOIN or WATCH is selected edits the address.
+pub struct LobbyScene {
+    selected: usize,
+    highlight: Timeline,
+    address: String,
+    status: String,
+    pending: Option<Pending>,
+    browser: Option<Browser>,
+    browse_timer: f32,
+    pub autostart: Option<LobbyAction>,
+}
+
+impl LobbyScene {
+    pub fn new() -> LobbyScene {
+        LobbyScene {
+            selected: 0,
+            highlight: highlight_timeline(),
+            address: String::new(),
+            status: String::new(),
+            pending: None,
+            browser: None,
+            browse_timer: 0.0,
+            autostart: None,
+        }
+    }
+
+    fn is_hosting(&self) -> bool {
+        matches!(self.pending, Some(Pending::Hosting { .. }))
+    }
+
+    // nothing to join while hosting
+    fn listed_games(&self) -> &[DiscoveredGame] {
+        match &self.browser {
+            Some(browser) if !self.is_hosting() => &browser.games,
+            _ => &[],
+        }
+    }
+
+    fn back_item(&self) -> usize {
+        FIRST_GAME_ITEM + self.listed_games().len()
+    }
+
+    fn browse(&mut self, settings: &NetSettings) {
+        if self.is_hosting() {
+            // frees the port for a copy of the game on this machine that wants to join
+            self.browser = None;
+            return;
+        }
+        if let Some(browser) = self.browser.as_mut() {
+            browser.update();
+            return;
+        }
+        self.browse_timer -= unsafe { DELTA_TIME };
+        if self.browse_timer <= 0.0 {
+            self.browse_timer = BROWSE_INTERVAL;
+            self.browser = Browser::new(settings).ok();
+        }
+    }
+
+    fn host(&mut self, settings: &NetSettings) {
+        match Connection::bind(settings.port) {
+            Ok(connection) => {
+                self.status = format!("waiting for a player on port {}", settings.port);
+                let announcer = match Announcer::new(settings) {
+                    Ok(announcer) => Some(announcer),
+                    Err(err) => {
+                        eprintln!("can't announce the game on the LAN: {}", err);
+                        None
+                    }
+                };
+                self.pending = Some(Pending::Hosting { connection, announcer });
+            }
+            Err(err) => self.status = format!("can't host on port {}: {}", settings.port, err),
+        }
+    }
+
+    fn join_listed(&mut self, game: DiscoveredGame) {
+        if !game.is_compatible() {
+            self.status = format!("{} needs protocol version {}", game.name, game.version);
+        } else {
+            // a full game can still be watched
+            self.address = game.address.to_string();
+            self.join(game.is_full());
+        }
+    }
+
+    fn join(&mut self, spectate: bool) {
+        let host = match self.address.to_socket_addrs().ok().and_then(|mut addresses| addresses.next()) {
+            Some(host) => host,
+            None => {
+                self.status = format!("unknown address {}", self.address);
+                return;
+            }
+        };
+        match Connection::bind(0) {
+            Ok(connection) => {
+                self.status = if spectate { format!("asking {} to watch", host) } else { format!("connecting to {}", host) };
+                self.pending = Some(Pending::Joining { connection, host, spectate, timer: 0.0, elapsed: 0.0 });
+            }
+            Err(err) => self.status = format!("can't open a socket: {}", err),
+        }
+    }
+
+    // Some once the other side answered
+    fn poll(&mut self, ctx: &mut Context) -> Option<NetSession> {
+        let delta_time = unsafe { DELTA_TIME };
+        let arena = (ctx.game.screen_width, ctx.game.screen_height);
+
+        match self.pending.as_mut()? {
+            Pending::Hosting { connection, announcer } => {
+                if let Some(announcer) = announcer.as_mut() {
+                    announcer.update(&ctx.settings.net, 1, 2);
+                }
+                while let Some((message, from)) = connection.receive() {
+                    if let Message::Watch { .. } = message {
+                        connection.send(&Message::Refused { reason: String::from("the match hasn't started") }, from);
+                    }
+                    if let Message::Join { version } = message {
+                        if version != PROTOCOL_VERSION {
+                            let reason = format!("host runs protocol version {}", PROTOCOL_VERSION);
+                            connection.send(&Message::Refused { reason }, from);
+                            continue;
+                        }
+                        let netcode = ctx.settings.net.netcode;
+                        let seed = rand::random();
+                        let countdown = ctx.game.serve.countdown;
+                        let welcome = Message::Welcome { arena, netcode, seed, countdown };
+                        connection.send(&welcome, from);
+
+                        // the arena the client was told about stays, even while the wipe to the match runs
+                        ctx.game.fixed_arena = true;
+                        ctx.game.start_match(seed);
+                        let connection = match self.pending.take() {
+                            Some(Pending::Hosting { connection, .. }) => connection,
+                            _ => return None,
+                        };
+                        return Some(match netcode {
+                            Netcode::Host => NetSession::Host(HostSession::new(connection, from, welcome)),
+                            Netcode::Rollback => NetSession::Rollback(RollbackSession::new(connection, from, Side::Left, &ctx.settings.net, Some(welcome))),
+                        });
+                    }
+                }
+            }
+            Pending::Joining { connection, host, spectate, timer, elapsed } => {
+                *elapsed += delta_time;
+                *timer -= delta_time;
+                if *timer <= 0.0 {
+                    *timer = JOIN_INTERVAL;
+                    let request = if *spectate { Message::Watch { version: PROTOCOL_VERSION } } else { Message::Join { version: PROTOCOL_VERSION } };
+                    connection.send(&request, *host);
+                }
+
+                while let Some((message, from)) = connection.receive() {
+                    if from != *host {
+                        continue;
+                    }
+                    match message {
+                        Message::Welcome { arena, .. } if !is_valid_arena(arena) => {
+                            self.status = format!("the host sent an arena of {}x{}", arena.0, arena.1);
+                            connection.send(&Message::Leave, *host);
+                            self.pending = None;
+                            return None;
+                        }
+                        Message::Welcome { arena, netcode, seed, countdown } => {
+                            // the client plays in the arena of the host, scaled to its own window, fixed before
+                            // the next frame would size it to the window again
+                            ctx.game.fixed_arena = true;
+                            ctx.game.screen_width = arena.0;
+                            ctx.game.screen_height = arena.1;
+                            ctx.game.serve.countdown = countdown;
+                            ctx.game.start_match(seed);
+
+                            let (connection, host, spectate) = match self.pending.take() {
+                                Some(Pending::Joining { connection, host, spectate, .. }) => (connection, host, spectate),
+                                _ => return None,
+                            };
+                            if spectate {
+                                return Some(NetSession::Spectator(SpectatorSession::new(connection, host)));
+                            }
+                            return Some(match netcode {
+                                Netcode::Host => NetSession::Client(ClientSession::new(connection, host)),
+                                Netcode::Rollback => NetSession::Rollback(RollbackSession::new(connection, host, Side::Right, &ctx.settings.net, None)),
+                            });
+                        }
+                        Message::Refused { reason } => {
+                            self.status = format!("refused: {}", reason);
+                            self.pending = None;
+                            return None;
+                        }
+                        _ => {}
+                    }
+                }
+
+                if *elapsed > ctx.settings.net.timeout {
+                    self.status = format!("no answer from {}", host);
+                    self.pending = None;
+                }
+            }
+        }
+        None
+    }
+
+    // true when a character was typed this frame
+    fn edit_address(&mut self, rl: &RaylibHandle) -> bool {
+        let mut typed = false;
+        loop {
+            let character = unsafe { ffi::GetCharPressed() };
+            if character <= 0 {
+                break;
+            }
+            match char::from_u32(character as u32) {
+                Some(character) if character.is_ascii_alphanumeric() || ".:-[]".contains(character) => {
+                    self.address.push(character);
+                    typed = true;
+                }
+                _ => {}
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.address.pop();
+        }
+        typed
+    }
+}
+
+impl Scene for LobbyScene {
+    fn enter(&mut self, ctx: &mut Context) {
+        self.selected = HOST_ITEM;
+        self.address = ctx.settings.net.address.clone();
+        // networked matches are two player ones
+        ctx.game.mode = GameMode::Classic;
+        self.status = String::new();
+        match self.autostart.take() {
+            Some(LobbyAction::Host) => self.host(&ctx.settings.net),
+            Some(LobbyAction::Join(address)) => {
+                self.address = address;
+                self.selected = JOIN_ITEM;
+                self.join(false);
+            }
+            Some(LobbyAction::Watch(address)) => {
+                self.address = address;
+                self.selected = WATCH_ITEM;
+                self.join(true);
+            }
+            None => {}
+        }
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) {
+        self.pending = None;
+        self.browser = None;
+    }
+
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand {
+        self.highlight.update(unsafe { DELTA_TIME });
+
+        if let Some(session) = self.poll(ctx) {
+            *ctx.net = Some(session);
+            return SceneCommand::Replace(GameState::Online, Transition::Wipe);
+        }
+        self.browse(&ctx.settings.net);
+        // games that stopped announcing leave the list
+        let back_item = self.back_item();
+        self.selected = self.selected.min(back_item);
+
+        // letters of the address would move the selection around too
+        let editing = (self.selected == JOIN_ITEM || self.selected == WATCH_ITEM) && self.pending.is_none();
+        if editing && self.edit_address(rl) {
+            return SceneCommand::None;
+        }
+
+        match poll_menu_input(rl) {
+            MenuInput::Up => self.selected = (self.selected + back_item) % (back_item + 1),
+            MenuInput::Down => self.selected = (self.selected + 1) % (back_item + 1),
+            // backspace edits the address instead
+            MenuInput::Back if editing && rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) => return SceneCommand::None,
+            MenuInput::Back if self.pending.is_some() => {
+                self.pending = None;
+                self.status = String::new();
+                return SceneCommand::None;
+            }
+            MenuInput::Back => return SceneCommand::Pop(Transition::Fade),
+            MenuInput::Confirm if self.pending.is_none() => {
+                match self.selected {
+                    HOST_ITEM => self.host(&ctx.settings.net),
+                    JOIN_ITEM | WATCH_ITEM => {
+                        // remembered for the next time
+                        ctx.settings.net.address = self.address.clone();
+                        if let Err(err) = ctx.settings.save(settings::SETTINGS_PATH) {
+                            eprintln!("failed to save settings: {}", err);
+                        }
+                        self.join(self.selected == WATCH_ITEM);
+                    }
+                    item if item < back_item => {
+                        let game = self.listed_games()[item - FIRST_GAME_ITEM].clone();
+                        self.join_listed(game);
+                    }
+                    _ => return SceneCommand::Pop(Transition::Fade),
+                }
+                return SceneCommand::None;
+            }
+            _ => return SceneCommand::None,
+        }
+        self.highlight.restart();
+        SceneCommand::None
+    }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        let theme = &ctx.game.theme;
+        let mut items = vec![
+            format!("HOST ON PORT {}", ctx.settings.net.port),
+            format!("JOIN {}", self.address),
+            format!("WATCH {}", self.address),
+        ];
+        for game in self.listed_games() {
+            let mut item = format!("{} {}/{} {}", game.name.to_uppercase(), game.players, game.capacity, game.address);
+            if !game.is_compatible() {
+                item += &format!(" V{}", game.version);
+            }
+            items.push(item);
+        }
+        items.push(String::from("BACK"));
+        draw_menu(d, theme, "ONLINE", &items, self.selected, self.highlight.value("scale"));
+
+        let status = self.status.to_uppercase();
+        let width = measure_text(&status, 30);
+        d.draw_text(&status, d.get_screen_width() / 2 - width / 2, d.get_screen_height() - 120, 30, theme.colors.accent);
+    }
+}
+
+/// A match against another machine, the host simulates and the client follows.
+pub struct OnlineScene {
+    // why the match ended early, shown until a button is pressed
+    disconnected: Option<String>,
+    // the host keeps announcing the game as full so it doesn't look gone
+    announcer: Option<Announcer>,
+}
+
+impl OnlineScene {
+    pub fn new() -> OnlineScene {
+        OnlineScene { disconnected: None, announcer: None }
+    }
+}
+
+impl Scene for OnlineScene {
+    fn enter(&mut self, ctx: &mut Context) {
+        self.disconnected = None;
+        // both sides play in the arena the host had when the match started, the lobby fixed it already
+        ctx.game.local_side = ctx.net.as_ref().map_or(Some(Side::Left), |session| session.local_side());
+        if ctx.game.local_side == Some(Side::Left) {
+            self.announcer = Announcer::new(&ctx.settings.net).ok();
+        }
+    }
+
+    fn exit(&mut self, ctx: &mut Context) {
+        if let Some(session) = ctx.net.take() {
+            session.leave();
+        }
+        self.announcer = None;
+        ctx.game.fixed_arena = false;
+        ctx.game.local_side = Some(Side::Left);
+        ctx.game.serve = ctx.settings.serve.clone();
+    }
+
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand {
+        if self.disconnected.is_some() {
+            return if any_pressed(rl) { SceneCommand::Reset(GameState::MainMenu, Transition::Fade) } else { SceneCommand::None };
+        }
+        // there is no pausing a match someone else plays too
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return SceneCommand::Reset(GameState::MainMenu, Transition::Fade);
+        }
+
+        let input = poll_player_input(rl);
+        let session = match ctx.net.as_mut() {
+            Some(session) => session,
+            None => return SceneCommand::Reset(GameState::MainMenu, Transition::Fade),
+        };
+        if let Err(reason) = session.update(ctx.game, input, &ctx.settings.net) {
+            self.disconnected = Some(reason);
+            return SceneCommand::None;
+        }
+        if let Some(announcer) = self.announcer.as_mut() {
+            announcer.update(&ctx.settings.net, 2, 2);
+        }
+
+        // the host decides on the rematch
+        if session.is_host() && ctx.game.is_match_over() && gameover::wants_rematch(rl) {
+            ctx.game.reset_match();
+        }
+        SceneCommand::None
+    }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        let arena = (ctx.game.screen_width, ctx.game.screen_height);
+        let camera = ctx.camera_effects.fitted_camera(arena, (d.get_screen_width(), d.get_screen_height()));
+        unsafe { ffi::BeginMode2D(camera); }
+        ctx.game.draw(d);
+        unsafe { ffi::EndMode2D(); }
+
+        if ctx.game.is_match_over() {
+            gameover::draw(d, ctx.game, ctx.stats);
+        }
+
+        let spectators = ctx.net.as_ref().map_or(0, |session| session.spectators());
+        if spectators > 0 {
+            let text = format!("{} WATCHING", spectators);
+            let width = measure_text(&text, 30);
+            d.draw_text(&text, d.get_screen_width() - width - 10, d.get_screen_height() - 40, 30, ctx.game.theme.colors.accent);
+        }
+
+        if let Some(tick) = ctx.net.as_ref().and_then(|session| session.desync()) {
+            let text = format!("DESYNC AT TICK {}", tick);
+            d.draw_text(&text, 10, d.get_screen_height() - 40, 30, ctx.game.theme.colors.accent);
+        }
+
+        if let Some(reason) = &self.disconnected {
+            let text = reason.to_uppercase();
+            let width = measure_text(&text, 60);
+            d.draw_rectangle(0, 0, d.get_screen_width(), d.get_screen_height(), ctx.game.theme.colors.background.fade(0.7));
+            d.draw_text(&text, d.get_screen_width() / 2 - width / 2, d.get_screen_height() / 2 - 30, 60, ctx.game.theme.colors.text);
+        }
+    }
+}
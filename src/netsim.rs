@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use rand::Rng;
+use raylib::prelude::*;
+use crate::net::Transport;
+
+/// What the simulator does to the packets this copy of the game sends, nothing by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkConditions {
+    /// milliseconds every packet is held back
+    pub latency: u32,
+    /// milliseconds the latency varies by, either way
+    pub jitter: u32,
+    /// chance from 0.0 to 1.0 that a packet is lost
+    pub loss: f32,
+    /// chance that a packet arrives twice
+    pub duplicate: f32,
+    /// chance that a packet is held back long enough for the next ones to overtake it
+    pub reorder: f32,
+}
+
+impl NetworkConditions {
+    pub const NONE: NetworkConditions = NetworkConditions { latency: 0, jitter: 0, loss: 0.0, duplicate: 0.0, reorder: 0.0 };
+
+    pub fn is_active(&self) -> bool {
+        *self != NetworkConditions::NONE
+    }
+}
+
+/// Conditions of every connection, set from the command line and changed in the debug overlay.
+pub static mut CONDITIONS: NetworkConditions = NetworkConditions::NONE;
+
+// milliseconds a reordered packet is held back on top of its latency
+const REORDER_DELAY: i64 = 40;
+
+struct DelayedPacket {
+    due: Instant,
+    bytes: Vec<u8>,
+    to: SocketAddr,
+}
+
+/// Wraps a transport and loses, repeats, delays and shuffles what is sent through it as
+/// `CONDITIONS` says, so netcode can be tried on one machine.
+///
+/// Held back packets leave on the next send or receive, the sessions do both every frame.
+pub struct SimulatedTransport<T: Transport> {
+    inner: T,
+    in_flight: RefCell<Vec<DelayedPacket>>,
+}
+
+impl<T: Transport> SimulatedTransport<T> {
+    pub fn new(inner: T) -> SimulatedTransport<T> {
+        SimulatedTransport { inner, in_flight: RefCell::new(Vec::new()) }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    // sends everything whose time came
+    fn flush(&self) {
+        let now = Instant::now();
+        let mut in_flight = self.in_flight.borrow_mut();
+        // stable, packets due at the same time keep their order
+        in_flight.sort_by_key(|packet| packet.due);
+        let due = in_flight.iter().take_while(|packet| packet.due <= now).count();
+        for packet in in_flight.drain(..due) {
+            self.inner.send_to(&packet.bytes, packet.to);
+        }
+    }
+}
+
+impl<T: Transport> Transport for SimulatedTransport<T> {
+    fn send_to(&self, bytes: &[u8], to: SocketAddr) {
+        let conditions = unsafe { CONDITIONS };
+        // packets still held back from before go first
+        if !conditions.is_active() && self.in_flight.borrow().is_empty() {
+            self.inner.send_to(bytes, to);
+            return;
+        }
+
+        let mut rng = rand::thread_rng();
+        let copies = if rng.gen::<f32>() < conditions.duplicate { 2 } else { 1 };
+        for _ in 0..copies {
+            if rng.gen::<f32>() < conditions.loss {
+                continue;
+            }
+            let jitter = conditions.jitter as i64;
+            let mut delay = conditions.latency as i64 + rng.gen_range(-jitter..=jitter);
+            if rng.gen::<f32>() < conditions.reorder {
+                delay += REORDER_DELAY;
+            }
+            self.in_flight.borrow_mut().push(DelayedPacket {
+                due: Instant::now() + Duration::from_millis(delay.max(0) as u64),
+                bytes: bytes.to_vec(),
+                to,
+            });
+        }
+        self.flush();
+    }
+
+    fn receive_from(&self, buffer: &mut [u8]) -> Option<(usize, SocketAddr)> {
+        self.flush();
+        self.inner.receive_from(buffer)
+    }
+}
+
+// debug overlay, F3 shows it, F4 picks a line and F5/F6 turn it down and up
+static mut OVERLAY_VISIBLE: bool = false;
+static mut OVERLAY_SELECTED: usize = 0;
+const OVERLAY_LINES: usize = 5;
+
+pub fn update_overlay(rl: &RaylibHandle) {
+    unsafe {
+        if rl.is_key_pressed(KeyboardKey::KEY_F3) {
+            OVERLAY_VISIBLE = !OVERLAY_VISIBLE;
+        }
+        if !OVERLAY_VISIBLE {
+            return;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_F4) {
+            OVERLAY_SELECTED = (OVERLAY_SELECTED + 1) % OVERLAY_LINES;
+        }
+
+        let direction = if rl.is_key_pressed(KeyboardKey::KEY_F6) {
+            1
+        } else if rl.is_key_pressed(KeyboardKey::KEY_F5) {
+            -1
+        } else {
+            return;
+        };
+        let percent = |chance: f32| (chance + 0.01 * direction as f32).clamp(0.0, 1.0);
+        match OVERLAY_SELECTED {
+            0 => CONDITIONS.latency = (CONDITIONS.latency as i32 + 10 * direction).max(0) as u32,
+            1 => CONDITIONS.jitter = (CONDITIONS.jitter as i32 + 5 * direction).max(0) as u32,
+            2 => CONDITIONS.loss = percent(CONDITIONS.loss),
+            3 => CONDITIONS.duplicate = percent(CONDITIONS.duplicate),
+            _ => CONDITIONS.reorder = percent(CONDITIONS.reorder),
+        }
+    }
+}
+
+pub fn draw_overlay(d: &mut RaylibDrawHandle) {
+    let (conditions, selected) = unsafe {
+        if !OVERLAY_VISIBLE {
+            return;
+        }
+        (CONDITIONS, OVERLAY_SELECTED)
+    };
+    let lines = [
+        format!("latency {} ms", conditions.latency),
+        format!("jitter {} ms", conditions.jitter),
+        format!("loss {:.0}%", conditions.loss * 100.0),
+        format!("duplicate {:.0}%", conditions.duplicate * 100.0),
+        format!("reorder {:.0}%", conditions.reorder * 100.0),
+    ];
+
+    d.draw_text("NETWORK (F4 select, F5/F6 change)", 10, 40, 20, Color::GRAY);
+    for (i, line) in lines.iter().enumerate() {
+        let color = if i == selected { Color::YELLOW } else { Color::GRAY };
+        d.draw_text(line, 20, 65 + i as i32 * 25, 20, color);
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use raylib::prelude::*;
+use serde::Deserialize;
+use crate::colorhelper;
+use crate::events::{EventSubscriber, GameEvent, Side};
+use crate::settings::ParticleSettings;
+
+/// How one kind of effect looks, loaded from res/particles.toml.
+#[derive(Deserialize, Clone, Debug)]
+pub struct EmitterConfig {
+    pub count: usize,
+    /// min and max seconds a particle lives
+    pub lifetime: (f32, f32),
+    /// min and max start speed in pixels per second
+    pub speed: (f32, f32),
+    /// degrees around the emit direction particles are spread over
+    pub spread: f32,
+    /// min and max radius in pixels
+    pub size: (f32, f32),
+    /// fraction of the velocity lost per second
+    #[serde(default)]
+    pub drag: f32,
+    #[serde(with = "colorhelper::hex_list")]
+    pub colors: Vec<Color>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Particle {
+    position: Vector2,
+    velocity: Vector2,
+    age: f32,
+    lifetime: f32,
+    size: f32,
+    drag: f32,
+    // index into ParticleSystem::ramps
+    ramp: usize,
+}
+
+/// Fixed size particle pool fed by gameplay events.
+#[derive(Clone)]
+pub struct ParticleSystem {
+    emitters: HashMap<String, (usize, EmitterConfig)>,
+    ramps: Vec<Vec<Color>>,
+    particles: Vec<Particle>,
+    // particles[..alive] are alive, dead ones are swapped to the end
+    alive: usize,
+    settings: ParticleSettings,
+    rng: StdRng,
+}
+
+impl ParticleSystem {
+    pub fn new(settings: &ParticleSettings) -> ParticleSystem {
+        let configs: HashMap<String, EmitterConfig> = toml::from_str(include_str!("../res/particles.toml"))
+            .expect("res/particles.toml is invalid");
+
+        let mut emitters = HashMap::new();
+        let mut ramps = Vec::new();
+        for (name, config) in configs {
+            ramps.push(config.colors.clone());
+            emitters.insert(name, (ramps.len() - 1, config));
+        }
+
+        ParticleSystem {
+            emitters,
+            ramps,
+            particles: vec![Particle::default(); settings.max_particles],
+            alive: 0,
+            settings: settings.clone(),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &ParticleSettings) {
+        if settings.max_particles != self.particles.len() {
+            self.particles = vec![Particle::default(); settings.max_particles];
+            self.alive = 0;
+        }
+        self.settings = settings.clone();
+    }
+
+    /// Makes the particles of every following effect come out the same for the same `seed`.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Spawns the particles of emitter `name` flying towards `direction` (in radians).
+    pub fn emit(&mut self, name: &str, position: Vector2, direction: f32) {
+        if !self.settings.enabled || self.particles.is_empty() {
+            return;
+        }
+        let (ramp, config) = match self.emitters.get(name) {
+            Some(emitter) => emitter,
+            None => return,
+        };
+
+        let rng = &mut self.rng;
+        let half_spread = config.spread.to_radians() / 2.0;
+        for _ in 0..config.count {
+            let angle = direction + rng.gen_range(-half_spread..=half_spread);
+            let speed = rng.gen_range(config.speed.0..=config.speed.1);
+            let particle = Particle {
+                position,
+                velocity: Vector2::new(angle.cos(), angle.sin()) * speed,
+                age: 0.0,
+                lifetime: rng.gen_range(config.lifetime.0..=config.lifetime.1),
+                size: rng.gen_range(config.size.0..=config.size.1),
+                drag: config.drag,
+                ramp: *ramp,
+            };
+
+            // when the pool is full the oldest slot gets reused
+            if self.alive < self.particles.len() {
+                self.particles[self.alive] = particle;
+                self.alive += 1;
+            } else {
+                let oldest = self.particles.iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| (a.age / a.lifetime).total_cmp(&(b.age / b.lifetime)))
+                    .map(|(index, _)| index)
+                    .unwrap_or(0);
+                self.particles[oldest] = particle;
+            }
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        let mut i = 0;
+        while i < self.alive {
+            let particle = &mut self.particles[i];
+            particle.age += delta_time;
+            if particle.age >= particle.lifetime {
+                // swap the dead particle out of the alive range
+                self.alive -= 1;
+                self.particles.swap(i, self.alive);
+                continue;
+            }
+
+            particle.velocity *= (1.0 - particle.drag * delta_time).max(0.0);
+            particle.position += particle.velocity * delta_time;
+            i += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.alive = 0;
+    }
+
+    /// Draws at most `budget` particles, spreading the skipped ones evenly over the pool.
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        if self.alive == 0 || self.settings.budget == 0 {
+            return;
+        }
+
+        let stride = (self.alive + self.settings.budget - 1) / self.settings.budget;
+        for particle in self.particles[..self.alive].iter().step_by(stride.max(1)) {
+            let t = particle.age / particle.lifetime;
+            let color = colorhelper::ramp(&self.ramps[particle.ramp], t);
+            d.draw_circle_v(particle.position, particle.size * (1.0 - t * 0.5), color);
+        }
+    }
+}
+
+impl EventSubscriber for ParticleSystem {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::PaddleHit { side, position, .. } => {
+                // sparks fly back into the field
+                let direction = match side {
+                    Side::Left => 0.0,
+                    Side::Right => std::f32::consts::PI,
+                    Side::Top => std::f32::consts::FRAC_PI_2,
+                    Side::Bottom => -std::f32::consts::FRAC_PI_2,
+                };
+                self.emit("paddle_hit", position, direction);
+            }
+            GameEvent::WallBounce { side, position, velocity } => {
+                // dust is kicked off the wall the ball bounced from
+                let direction = match side {
+                    Side::Left | Side::Right => if velocity.x > 0.0 { 0.0 } else { std::f32::consts::PI },
+                    Side::Top | Side::Bottom => if velocity.y > 0.0 { std::f32::consts::FRAC_PI_2 } else { -std::f32::consts::FRAC_PI_2 },
+                };
+                self.emit("wall_bounce", position, direction);
+            }
+            GameEvent::Scored { position, .. } => {
+                self.emit("goal", position, 0.0);
+            }
+            GameEvent::Countdown { .. } | GameEvent::Serve { .. } | GameEvent::MatchWon { .. } => {}
+        }
+    }
+}
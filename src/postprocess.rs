@@ -0,0 +1,189 @@
+use std::ptr::null;
+use raylib::ffi::{BeginShaderMode, BeginTextureMode, ClearBackground, DrawTextureRec, EndShaderMode, EndTextureMode, FileExists, GetFileModTime, GetShaderLocation, LoadRenderTexture, LoadShader, RenderTexture2D, SetShaderValue, Shader, UnloadRenderTexture, UnloadShader};
+use raylib::prelude::*;
+use crate::const_c;
+use crate::settings::PostProcessSettings;
+
+// seconds between checks for changed shader files in debug builds
+const HOT_RELOAD_INTERVAL: f32 = 0.5;
+
+struct Pass {
+    path: String,
+    shader: Shader,
+    modified: i64,
+    resolution_location: i32,
+    time_location: i32,
+}
+
+impl Pass {
+    fn load(path: String) -> Option<Pass> {
+        unsafe {
+            if !FileExists(const_c!(path.as_str())) {
+                eprintln!("shader {} not found", path);
+                return None;
+            }
+            let shader = LoadShader(null(), const_c!(path.as_str()));
+            Some(Pass {
+                modified: GetFileModTime(const_c!(path.as_str())) as i64,
+                resolution_location: GetShaderLocation(shader, const_c!("resolution")),
+                time_location: GetShaderLocation(shader, const_c!("time")),
+                path,
+                shader,
+            })
+        }
+    }
+}
+
+/// Renders the frame into an off-screen texture and runs it through a chain of fragment shaders.
+pub struct PostProcess {
+    passes: Vec<Pass>,
+    // the scene is drawn into the first target, passes ping-pong between both
+    targets: Option<[RenderTexture2D; 2]>,
+    width: i32,
+    height: i32,
+    hot_reload_timer: f32,
+}
+
+impl PostProcess {
+    pub fn new(settings: &PostProcessSettings) -> PostProcess {
+        let passes = if settings.enabled {
+            settings.passes.iter()
+                .filter_map(|name| Pass::load(format!("{}/{}.fs", settings.shader_directory, name)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        PostProcess {
+            passes,
+            targets: None,
+            width: 0,
+            height: 0,
+            hot_reload_timer: 0.0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.passes.is_empty()
+    }
+
+    /// Redirects drawing into the off-screen texture, call inside the frame before drawing the scene.
+    pub fn begin(&mut self, width: i32, height: i32) {
+        if !self.is_active() {
+            return;
+        }
+
+        if self.targets.is_none() || self.width != width || self.height != height {
+            self.unload_targets();
+            unsafe {
+                self.targets = Some([LoadRenderTexture(width, height), LoadRenderTexture(width, height)]);
+            }
+            self.width = width;
+            self.height = height;
+        }
+
+        unsafe {
+            BeginTextureMode(self.targets.unwrap()[0]);
+            ClearBackground(Color::BLACK.into());
+        }
+    }
+
+    /// Runs the scene through every pass, the last one draws to the screen.
+    pub fn end(&mut self, time: f32) {
+        let targets = match self.targets {
+            Some(targets) if self.is_active() => targets,
+            _ => return,
+        };
+
+        let resolution = [self.width as f32, self.height as f32];
+        // render textures are upside down, a negative source height flips them back
+        let source = ffi::Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: self.width as f32,
+            height: -self.height as f32,
+        };
+
+        unsafe {
+            EndTextureMode();
+
+            let last = self.passes.len() - 1;
+            for (i, pass) in self.passes.iter().enumerate() {
+                let input = targets[i % 2];
+                if i != last {
+                    BeginTextureMode(targets[(i + 1) % 2]);
+                    ClearBackground(Color::BLACK.into());
+                }
+
+                if pass.resolution_location >= 0 {
+                    SetShaderValue(pass.shader,
+                                   pass.resolution_location,
+                                   resolution.as_ptr() as *const _,
+                                   ffi::ShaderUniformDataType::SHADER_UNIFORM_VEC2 as i32);
+                }
+                if pass.time_location >= 0 {
+                    SetShaderValue(pass.shader,
+                                   pass.time_location,
+                                   &time as *const f32 as *const _,
+                                   ffi::ShaderUniformDataType::SHADER_UNIFORM_FLOAT as i32);
+                }
+
+                BeginShaderMode(pass.shader);
+                DrawTextureRec(input.texture, source, Vector2::zero().into(), Color::WHITE.into());
+                EndShaderMode();
+
+                if i != last {
+                    EndTextureMode();
+                }
+            }
+        }
+    }
+
+    /// Reloads shaders whose file changed on disk, only in debug builds.
+    pub fn hot_reload(&mut self, delta_time: f32) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        self.hot_reload_timer -= delta_time;
+        if self.hot_reload_timer > 0.0 {
+            return;
+        }
+        self.hot_reload_timer = HOT_RELOAD_INTERVAL;
+
+        for pass in self.passes.iter_mut() {
+            let modified = unsafe { GetFileModTime(const_c!(pass.path.as_str())) as i64 };
+            if modified == pass.modified {
+                continue;
+            }
+
+            match Pass::load(pass.path.clone()) {
+                Some(reloaded) => {
+                    unsafe { UnloadShader(pass.shader); }
+                    *pass = reloaded;
+                }
+                // keeps the old shader and says so once, until the file changes again
+                None => pass.modified = modified,
+            }
+        }
+    }
+
+    fn unload_targets(&mut self) {
+        if let Some(targets) = self.targets.take() {
+            unsafe {
+                UnloadRenderTexture(targets[0]);
+                UnloadRenderTexture(targets[1]);
+            }
+        }
+    }
+}
+
+impl Drop for PostProcess {
+    // toggling the filter in the options replaces the whole chain
+    fn drop(&mut self) {
+        for pass in self.passes.iter() {
+            unsafe { UnloadShader(pass.shader); }
+        }
+        self.unload_targets();
+    }
+}
@@ -0,0 +1,470 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use raylib::prelude::*;
+use crate::events;
+use crate::game::{is_valid_arena, Game, GameMode, Snapshot, TICK, TICK_RATE};
+use crate::input::{any_pressed, PaddleInput};
+use crate::scene::{Context, Scene, SceneCommand, Transition};
+use crate::settings::Settings;
+use crate::time::DELTA_TIME;
+
+const MAGIC: &[u8; 4] = b"P50R";
+const VERSION: u8 = 2;
+// lengths read from a file are checked against these before anything is allocated for them
+const MAX_SETTINGS_LENGTH: u64 = 64 * 1024;
+// a day of ticks
+const MAX_TICKS: u64 = TICK_RATE as u64 * 60 * 60 * 24;
+/// Extension of replay files.
+pub const EXTENSION: &str = "p50r";
+
+// playback speeds, UP and DOWN step through them
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const NORMAL_SPEED: usize = 2;
+// seconds LEFT and RIGHT jump
+const SCRUB_STEP: f32 = 5.0;
+
+/// Everything needed to simulate a match again: the mode, the seed, the arena, the settings and
+/// the paddle input of every tick.
+///
+/// Files start with "P50R", a version byte, the mode, the seed, the arena size and the settings as
+/// toml, followed by the inputs as run-length encoded (direction, ticks) pairs. Version 1 files
+/// have no mode byte and hold classic matches.
+#[derive(Clone)]
+pub struct Replay {
+    pub mode: GameMode,
+    pub seed: u64,
+    pub arena: (i32, i32),
+    pub settings: Settings,
+    pub inputs: Vec<PaddleInput>,
+}
+
+impl Replay {
+    /// Starts recording the match `game` just started.
+    pub fn new(game: &Game, settings: &Settings) -> Replay {
+        // the serve the match is simulated with, not what the options say now
+        let mut settings = settings.clone();
+        settings.serve = game.serve.clone();
+        settings.four_player = game.four_player.clone();
+        settings.multi_ball = game.multi_ball.clone();
+        Replay {
+            mode: game.mode,
+            seed: game.seed(),
+            arena: (game.screen_width, game.screen_height),
+            settings,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, input: PaddleInput, ticks: u32) {
+        self.inputs.extend(std::iter::repeat(input).take(ticks as usize));
+    }
+
+    /// Length in seconds.
+    pub fn duration(&self) -> f32 {
+        self.inputs.len() as f32 * TICK
+    }
+
+    pub fn load(path: &str) -> io::Result<Replay> {
+        Replay::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the replay to `directory`, named after the current time.
+    pub fn save(&self, directory: &str) -> io::Result<String> {
+        std::fs::create_dir_all(directory)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let path = Path::new(directory).join(format!("{}.{}", timestamp, EXTENSION));
+
+        let mut writer = BufWriter::new(File::create(&path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(path.display().to_string())
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let settings = toml::to_string(&self.settings)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&[mode_byte(self.mode)])?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&self.arena.0.to_le_bytes())?;
+        writer.write_all(&self.arena.1.to_le_bytes())?;
+        write_varint(writer, settings.len() as u64)?;
+        writer.write_all(settings.as_bytes())?;
+
+        write_varint(writer, self.inputs.len() as u64)?;
+        let mut ticks = self.inputs.iter().map(|input| direction_byte(*input)).peekable();
+        while let Some(direction) = ticks.next() {
+            let mut run = 1;
+            while ticks.peek() == Some(&direction) {
+                ticks.next();
+                run += 1;
+            }
+            writer.write_all(&[direction as u8])?;
+            write_varint(writer, run)?;
+        }
+        Ok(())
+    }
+
+    pub fn read(reader: &mut impl Read) -> io::Result<Replay> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a replay file"));
+        }
+        let version = read_bytes::<1>(reader)?[0];
+        let mode = match version {
+            1 => GameMode::Classic,
+            VERSION => read_mode(read_bytes::<1>(reader)?[0])?,
+            _ => return Err(invalid(&format!("unsupported replay version {}", version))),
+        };
+
+        let seed = u64::from_le_bytes(read_bytes(reader)?);
+        let arena = (i32::from_le_bytes(read_bytes(reader)?), i32::from_le_bytes(read_bytes(reader)?));
+        if !is_valid_arena(arena) {
+            return Err(invalid(&format!("invalid arena {}x{}", arena.0, arena.1)));
+        }
+
+        let length = read_varint(reader)?;
+        if length > MAX_SETTINGS_LENGTH {
+            return Err(invalid("settings too long"));
+        }
+        let mut settings = vec![0; length as usize];
+        reader.read_exact(&mut settings)?;
+        let settings = String::from_utf8(settings).map_err(|_| invalid("settings are not utf-8"))?;
+        let settings: Settings = toml::from_str(&settings).map_err(|err| invalid(&err.to_string()))?;
+
+        let count = read_varint(reader)?;
+        if count > MAX_TICKS {
+            return Err(invalid("replay too long"));
+        }
+        let count = count as usize;
+        let mut inputs = Vec::new();
+        while inputs.len() < count {
+            let direction = read_bytes::<1>(reader)?[0] as i8;
+            if !(-1..=1).contains(&direction) {
+                return Err(invalid(&format!("invalid direction {}", direction)));
+            }
+            let run = read_varint(reader)?;
+            if run > (count - inputs.len()) as u64 {
+                return Err(invalid("more inputs than ticks"));
+            }
+            inputs.extend(std::iter::repeat(PaddleInput { direction: direction as f32 }).take(run as usize));
+        }
+
+        Ok(Replay { mode, seed, arena, settings, inputs })
+    }
+}
+
+// the simulation only ever sees -1.0, 0.0 and 1.0
+fn direction_byte(input: PaddleInput) -> i8 {
+    input.direction.round().clamp(-1.0, 1.0) as i8
+}
+
+fn mode_byte(mode: GameMode) -> u8 {
+    match mode {
+        GameMode::Classic => 0,
+        GameMode::FourPlayer => 1,
+        GameMode::MultiBall => 2,
+    }
+}
+
+fn read_mode(byte: u8) -> io::Result<GameMode> {
+    match byte {
+        0 => Ok(GameMode::Classic),
+        // four player matches aren't recorded, a replay only holds the left paddle's inputs
+        2 => Ok(GameMode::MultiBall),
+        _ => Err(invalid(&format!("unknown mode {}", byte))),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+// 7 bits per byte, the high bit marks that more bytes follow
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(reader: &mut impl Read) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = read_bytes::<1>(reader)?[0];
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint too long"))
+}
+
+/// Plays a replay file back by simulating it again, with pause, scrubbing and speed control.
+pub struct ReplayScene {
+    replay: Replay,
+    tick: usize,
+    speed: usize,
+    paused: bool,
+    accumulator: f32,
+}
+
+impl ReplayScene {
+    pub fn new(replay: Replay) -> ReplayScene {
+        ReplayScene {
+            replay,
+            tick: 0,
+            speed: NORMAL_SPEED,
+            paused: false,
+            accumulator: 0.0,
+        }
+    }
+
+    fn restart(&mut self, game: &mut Game) {
+        game.start_match(self.replay.seed);
+        self.tick = 0;
+        self.accumulator = 0.0;
+    }
+
+    fn step(&mut self, game: &mut Game) {
+        if let Some(input) = self.replay.inputs.get(self.tick) {
+            game.step(*input);
+            self.tick += 1;
+        }
+    }
+
+    /// Jumps to `tick` by simulating from the start when going backwards, the skipped ticks stay silent.
+    fn seek(&mut self, game: &mut Game, tick: usize) {
+        let tick = tick.min(self.replay.inputs.len());
+        if tick < self.tick {
+            self.restart(game);
+        }
+        while self.tick < tick {
+            self.step(game);
+        }
+        events::discard();
+        game.particles.clear();
+    }
+}
+
+impl Scene for ReplayScene {
+    fn enter(&mut self, ctx: &mut Context) {
+        // the simulation runs with the arena and settings of the recording
+        let game = &mut *ctx.game;
+        game.fixed_arena = true;
+        game.screen_width = self.replay.arena.0;
+        game.screen_height = self.replay.arena.1;
+        game.serve = self.replay.settings.serve.clone();
+        game.mode = self.replay.mode;
+        game.four_player = self.replay.settings.four_player.clone();
+        game.multi_ball = self.replay.settings.multi_ball.clone();
+        self.restart(game);
+    }
+
+    fn exit(&mut self, ctx: &mut Context) {
+        ctx.game.fixed_arena = false;
+        ctx.game.serve = ctx.settings.serve.clone();
+        ctx.game.four_player = ctx.settings.four_player.clone();
+        ctx.game.multi_ball = ctx.settings.multi_ball.clone();
+    }
+
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand {
+        let game = &mut *ctx.game;
+        let scrub = (SCRUB_STEP / TICK) as usize;
+
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return SceneCommand::Quit;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+            self.paused = !self.paused;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.speed = self.speed.saturating_sub(1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
+            self.seek(game, self.tick.saturating_sub(scrub));
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+            self.seek(game, self.tick + scrub);
+        }
+        // step a single tick while paused
+        if self.paused && rl.is_key_pressed(KeyboardKey::KEY_PERIOD) {
+            self.step(game);
+        }
+
+        if !self.paused && !ctx.camera_effects.is_frozen() {
+            self.accumulator += unsafe { DELTA_TIME } * SPEEDS[self.speed];
+            while self.accumulator >= TICK && self.tick < self.replay.inputs.len() {
+                self.accumulator -= TICK;
+                self.step(game);
+            }
+        }
+        SceneCommand::None
+    }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        let window = (d.get_screen_width(), d.get_screen_height());
+        let camera = ctx.camera_effects.fitted_camera(self.replay.arena, window);
+
+        unsafe { ffi::BeginMode2D(camera); }
+        ctx.game.draw(d);
+        unsafe { ffi::EndMode2D(); }
+
+        self.draw_controls(d, ctx, Vector2::new(window.0 as f32, window.1 as f32));
+    }
+}
+
+impl ReplayScene {
+    fn draw_controls(&self, d: &mut RaylibDrawHandle, ctx: &Context, window: Vector2) {
+        let colors = ctx.game.theme.colors;
+        let progress = if self.replay.inputs.is_empty() { 1.0 } else { self.tick as f32 / self.replay.inputs.len() as f32 };
+
+        let bar = Vector2::new(window.x - 80.0, 8.0);
+        let bar_position = Vector2::new(40.0, window.y - 40.0);
+        d.draw_rectangle_v(bar_position, bar, colors.text.fade(0.3));
+        d.draw_rectangle_v(bar_position, Vector2::new(bar.x * progress, bar.y), colors.accent);
+
+        let state = if self.paused { "PAUSED" } else { "REPLAY" };
+        let text = format!("{}  {:.2}x  {} / {}",
+                           state,
+                           SPEEDS[self.speed],
+                           format_time(self.tick as f32 * TICK),
+                           format_time(self.replay.duration()));
+        d.draw_text(&text, bar_position.x as i32, bar_position.y as i32 - 30, 20, colors.text);
+
+        let help = "SPACE pause  . step  LEFT/RIGHT seek  UP/DOWN speed  ESC quit";
+        let help_width = measure_text(help, 20);
+        d.draw_text(help, (bar_position.x + bar.x) as i32 - help_width, bar_position.y as i32 - 30, 20, colors.text.fade(0.6));
+    }
+}
+
+fn format_time(seconds: f32) -> String {
+    format!("{}:{:02}", seconds as u32 / 60, seconds as u32 % 60)
+}
+
+/// Records every match played and saves it when the match is left.
+pub struct Recorder {
+    pub directory: String,
+    recording: Option<Replay>,
+}
+
+impl Recorder {
+    pub fn new(directory: &str) -> Recorder {
+        Recorder {
+            directory: directory.to_string(),
+            recording: None,
+        }
+    }
+
+    pub fn start(&mut self, game: &Game, settings: &Settings) {
+        self.recording = Some(Replay::new(game, settings));
+    }
+
+    pub fn record(&mut self, input: PaddleInput, ticks: u32) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.record(input, ticks);
+        }
+    }
+
+    /// Saves the recording, matches left before the first tick are dropped.
+    pub fn finish(&mut self) {
+        let recording = match self.recording.take() {
+            Some(recording) if !recording.inputs.is_empty() => recording,
+            _ => return,
+        };
+        match recording.save(&self.directory) {
+            Ok(path) => eprintln!("saved replay {}", path),
+            Err(err) => eprintln!("failed to save replay: {}", err),
+        }
+    }
+}
+
+// seconds the REPLAY label takes to blink once
+const BLINK_TIME: f32 = 1.0;
+
+/// Slow motion replay of the end of a rally after a goal, any button skips it.
+pub struct InstantReplayScene {
+    snapshots: Vec<Snapshot>,
+    time: f32,
+}
+
+impl InstantReplayScene {
+    pub fn new() -> InstantReplayScene {
+        InstantReplayScene {
+            snapshots: Vec::new(),
+            time: 0.0,
+        }
+    }
+
+    fn index(&self) -> usize {
+        ((self.time / TICK) as usize).min(self.snapshots.len().saturating_sub(1))
+    }
+}
+
+impl Scene for InstantReplayScene {
+    fn enter(&mut self, ctx: &mut Context) {
+        self.snapshots = ctx.game.take_goal_replay();
+        self.time = 0.0;
+    }
+
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand {
+        self.time += unsafe { DELTA_TIME } * ctx.game.instant_replay.speed;
+
+        let finished = self.time >= self.snapshots.len() as f32 * TICK;
+        if finished || any_pressed(rl) {
+            SceneCommand::Pop(Transition::Fade)
+        } else {
+            SceneCommand::None
+        }
+    }
+
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        let snapshot = match self.snapshots.get(self.index()) {
+            Some(snapshot) => snapshot.clone(),
+            None => return,
+        };
+        let colors = ctx.game.theme.colors;
+        let arena = (ctx.game.screen_width, ctx.game.screen_height);
+        let camera = ctx.camera_effects.fitted_camera(arena, (d.get_screen_width(), d.get_screen_height()));
+
+        unsafe { ffi::BeginMode2D(camera); }
+        // the path of every ball so far, not across the jump when a ball left and the others moved up
+        let jump = ctx.game.screen_width as f32 / 4.0;
+        for pair in self.snapshots[..=self.index()].windows(2) {
+            for (from, to) in pair[0].ball_positions.iter().zip(pair[1].ball_positions.iter()) {
+                if from.distance_to(*to) < jump {
+                    d.draw_line_ex(*from, *to, 3.0, colors.accent.fade(0.6));
+                }
+            }
+        }
+        ctx.game.draw_snapshot(d, &snapshot);
+        unsafe { ffi::EndMode2D(); }
+
+        // blinking label in the top left corner
+        if self.time % BLINK_TIME < BLINK_TIME * 0.7 {
+            d.draw_circle(60, 60, 12.0, Color::RED);
+            d.draw_text("REPLAY", 85, 45, 30, colors.text);
+        }
+        let hint = "PRESS ANY BUTTON TO SKIP";
+        let width = measure_text(hint, 20);
+        d.draw_text(hint, d.get_screen_width() / 2 - width / 2, d.get_screen_height() - 50, 20, colors.text.fade(0.6));
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use crate::events::{self, emit, GameEvent, Side};
+use crate::game::{Game, SimulationState, TICK};
+use crate::input::PaddleInput;
+use crate::net::{Connection, Message, MAX_INPUTS};
+use crate::settings::NetSettings;
+use crate::spectate::Spectators;
+use crate::time::DELTA_TIME;
+
+// ticks the simulation may run ahead of the last confirmed remote input, it waits for the
+// other player beyond that
+const MAX_ROLLBACK: u32 = 16;
+// a checksum of the confirmed state is compared every this many ticks
+const CHECKSUM_INTERVAL: u32 = 60;
+// seconds of ticks run at most in one frame
+const MAX_CATCH_UP: f32 = 0.1;
+
+/// Peer-to-peer match where both sides simulate everything.
+///
+/// Local inputs apply after `input_delay` ticks, missing remote inputs are predicted to repeat
+/// the last known one. When a remote input arrives that differs from the prediction, the state
+/// saved before that tick is loaded and the ticks since are simulated again.
+pub struct RollbackSession {
+    connection: Connection,
+    peer: SocketAddr,
+    // the host sends it again when the other player didn't get it
+    welcome: Option<Message>,
+    // only the host has any
+    spectators: Option<Spectators>,
+    local_side: Side,
+    // ticks simulated so far, the next tick to simulate
+    tick: u32,
+    accumulator: f32,
+    // inputs by tick, the local ones reach the input delay ahead
+    local_inputs: Vec<PaddleInput>,
+    remote_inputs: Vec<PaddleInput>,
+    // remote inputs each simulated tick used, predicted or not
+    used_inputs: Vec<PaddleInput>,
+    // last tick of our inputs the peer has, from its acks
+    peer_ack: u32,
+    // state at the start of each of the last ticks, oldest first
+    states: VecDeque<(u32, SimulationState)>,
+    // oldest tick simulated with a wrong prediction
+    rollback_to: Option<u32>,
+    // goals and wins by tick, held back until the inputs of their tick are known
+    held_events: Vec<(u32, GameEvent)>,
+    // local checksums waiting for the peer's and the other way round
+    local_checksums: VecDeque<(u32, u64)>,
+    remote_checksums: VecDeque<(u32, u64)>,
+    last_checksum: u32,
+    desync: Option<u32>,
+    silence: f32,
+}
+
+impl RollbackSession {
+    pub fn new(connection: Connection,
+               peer: SocketAddr,
+               local_side: Side,
+               settings: &NetSettings,
+               welcome: Option<Message>) -> RollbackSession {
+        RollbackSession {
+            connection,
+            peer,
+            spectators: welcome.clone().map(Spectators::new),
+            welcome,
+            local_side,
+            tick: 0,
+            accumulator: 0.0,
+            // nobody moves during the delay at the start
+            local_inputs: vec![PaddleInput::default(); settings.input_delay as usize],
+            remote_inputs: Vec::new(),
+            used_inputs: Vec::new(),
+            peer_ack: 0,
+            states: VecDeque::new(),
+            rollback_to: None,
+            held_events: Vec::new(),
+            local_checksums: VecDeque::new(),
+            remote_checksums: VecDeque::new(),
+            last_checksum: 0,
+            desync: None,
+            silence: 0.0,
+        }
+    }
+
+    pub fn local_side(&self) -> Side {
+        self.local_side
+    }
+
+    pub fn spectators(&self) -> usize {
+        self.spectators.as_ref().map_or(0, |spectators| spectators.count())
+    }
+
+    /// First tick whose state didn't match the other player's, the match can't be trusted after it.
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+
+    pub fn update(&mut self, game: &mut Game, input: PaddleInput, settings: &NetSettings) -> Result<(), String> {
+        self.silence += unsafe { DELTA_TIME };
+        while let Some((message, from)) = self.connection.receive() {
+            if from != self.peer {
+                if let Some(spectators) = self.spectators.as_mut() {
+                    spectators.receive(&self.connection, message, from, settings);
+                }
+                continue;
+            }
+            self.silence = 0.0;
+
+            match message {
+                Message::Join { .. } => {
+                    if let Some(welcome) = &self.welcome {
+                        self.connection.send(welcome, from);
+                    }
+                }
+                Message::Inputs { first_tick, ack, inputs } => {
+                    self.peer_ack = self.peer_ack.max(ack);
+                    self.receive_inputs(first_tick, &inputs);
+                }
+                Message::Checksum { tick, checksum } => self.remote_checksums.push_back((tick, checksum)),
+                Message::Leave => return Err(String::from("the other player left")),
+                _ => {}
+            }
+        }
+        if self.silence > settings.timeout {
+            return Err(String::from("connection lost"));
+        }
+
+        self.roll_back(game);
+        // before advancing, which could push states out that were just confirmed
+        self.check_sync();
+        self.advance(game, input);
+        self.release_events();
+        self.send_inputs();
+        // spectators see the predicted state, a wrong guess is corrected a moment later
+        if let Some(spectators) = self.spectators.as_mut() {
+            spectators.update(&self.connection, self.tick, game.match_state(), events::queued(), settings);
+        }
+        Ok(())
+    }
+
+    fn receive_inputs(&mut self, first_tick: u32, inputs: &[PaddleInput]) {
+        // the peer's ticks start at 1, index 0 is tick 1
+        for (i, input) in inputs.iter().enumerate() {
+            // tick 0 or past the last tick only comes from a broken packet
+            let index = match first_tick.checked_add(i as u32).and_then(|tick| tick.checked_sub(1)) {
+                Some(index) => index as usize,
+                None => return,
+            };
+            if index != self.remote_inputs.len() {
+                // already known, or a gap a later packet fills
+                continue;
+            }
+            self.remote_inputs.push(*input);
+            if index < self.used_inputs.len() && self.used_inputs[index] != *input {
+                let tick = index as u32;
+                self.rollback_to = Some(self.rollback_to.map_or(tick, |oldest| oldest.min(tick)));
+            }
+        }
+    }
+
+    // goes back to the oldest mispredicted tick and simulates up to the present again
+    fn roll_back(&mut self, game: &mut Game) {
+        let tick = match self.rollback_to.take() {
+            Some(tick) => tick,
+            None => return,
+        };
+        let state = match self.states.iter().find(|(saved, _)| *saved == tick) {
+            Some((_, state)) => state.clone(),
+            None => return,
+        };
+
+        let present = self.tick;
+        game.load_state(&state);
+        self.states.retain(|(saved, _)| *saved < tick);
+        // the goals of these ticks come again if they still happen
+        self.held_events.retain(|(held, _)| *held < tick);
+        self.tick = tick;
+        // a goal that was missed before may end the match earlier now
+        while self.tick < present && !game.is_match_over() {
+            self.step(game, true);
+        }
+    }
+
+    fn advance(&mut self, game: &mut Game, input: PaddleInput) {
+        self.accumulator = (self.accumulator + unsafe { DELTA_TIME }).min(MAX_CATCH_UP);
+        while self.accumulator >= TICK {
+            // wait for the other player instead of predicting too far
+            if self.tick >= self.remote_inputs.len() as u32 + MAX_ROLLBACK || game.is_match_over() {
+                self.accumulator = 0.0;
+                break;
+            }
+            self.accumulator -= TICK;
+            self.local_inputs.push(input);
+            self.step(game, false);
+        }
+    }
+
+    // `again` when the tick was simulated before with a different prediction
+    fn step(&mut self, game: &mut Game, again: bool) {
+        let index = self.tick as usize;
+        let local = self.local_inputs[index];
+        // a missing input probably stays what it was
+        let remote = match self.remote_inputs.get(index) {
+            Some(input) => *input,
+            None => self.remote_inputs.last().copied().unwrap_or_default(),
+        };
+        self.used_inputs.truncate(index);
+        self.used_inputs.push(remote);
+
+        self.states.push_back((self.tick, game.save_state()));
+        while self.states.len() > MAX_ROLLBACK as usize + 1 {
+            self.states.pop_front();
+        }
+
+        let queued = events::count();
+        match self.local_side {
+            Side::Left => game.step_versus(local, Some(remote)),
+            // networked matches only have a left and a right player
+            _ => game.step_versus(remote, Some(local)),
+        }
+
+        // a wrong prediction may take goals and wins back, they wait for the inputs of their tick
+        for event in events::take_from(queued) {
+            match event {
+                GameEvent::Scored { .. } | GameEvent::MatchWon { .. } => self.held_events.push((self.tick, event)),
+                // the sounds and effects of the rest already played when the tick was predicted
+                _ if again => {}
+                _ => emit(event),
+            }
+        }
+        self.tick += 1;
+    }
+
+    // emits the held goals and wins no rollback can change anymore
+    fn release_events(&mut self) {
+        let confirmed = self.remote_inputs.len() as u32;
+        let (ready, held): (Vec<_>, Vec<_>) = std::mem::take(&mut self.held_events)
+            .into_iter()
+            .partition(|(tick, _)| *tick < confirmed);
+        self.held_events = held;
+        for (_, event) in ready {
+            emit(event);
+        }
+    }
+
+    fn send_inputs(&mut self) {
+        // the oldest inputs the peer hasn't acknowledged, it can't use any after a gap
+        let last = self.local_inputs.len() as u32;
+        let first = self.peer_ack.saturating_add(1);
+        let end = last.min(first.saturating_add(MAX_INPUTS as u32 - 1));
+        let inputs = if first <= end { self.local_inputs[first as usize - 1..end as usize].to_vec() } else { Vec::new() };
+        self.connection.send(&Message::Inputs {
+            first_tick: first,
+            ack: self.remote_inputs.len() as u32,
+            inputs,
+        }, self.peer);
+    }
+
+    fn check_sync(&mut self) {
+        // the state at the start of a tick is final once every input before it is known
+        let confirmed = (self.remote_inputs.len() as u32).min(self.tick);
+        let due: Vec<(u32, u64)> = self.states.iter()
+            .filter(|(tick, _)| *tick > self.last_checksum && *tick <= confirmed && *tick % CHECKSUM_INTERVAL == 0)
+            .map(|(tick, state)| (*tick, state.checksum()))
+            .collect();
+        for (tick, checksum) in due {
+            self.last_checksum = tick;
+            self.local_checksums.push_back((tick, checksum));
+            self.connection.send(&Message::Checksum { tick, checksum }, self.peer);
+        }
+
+        // compare whatever both sides have, the older leftovers won't get a partner anymore
+        while let (Some(local), Some(remote)) = (self.local_checksums.front().copied(), self.remote_checksums.front().copied()) {
+            if local.0 < remote.0 {
+                self.local_checksums.pop_front();
+            } else if remote.0 < local.0 {
+                self.remote_checksums.pop_front();
+            } else {
+                if local.1 != remote.1 && self.desync.is_none() {
+                    eprintln!("desync at tick {}: {:016x} here, {:016x} on the other side", local.0, local.1, remote.1);
+                    self.desync = Some(local.0);
+                }
+                self.local_checksums.pop_front();
+                self.remote_checksums.pop_front();
+            }
+        }
+    }
+
+    pub fn leave(&self) {
+        self.connection.send(&Message::Leave, self.peer);
+        if let Some(spectators) = &self.spectators {
+            spectators.leave(&self.connection);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use raylib::prelude::*;
+use crate::audiosystem::SoundManager;
+use crate::camera::CameraEffects;
+use crate::game::Game;
+use crate::gamestate::GameState;
+use crate::netplay::NetSession;
+use crate::postprocess::PostProcess;
+use crate::settings::Settings;
+use crate::stats::Stats;
+use crate::theme::ThemeManager;
+use crate::time::DELTA_TIME;
+use crate::tween::{Ease, Timeline};
+
+/// Everything a scene may read or change, borrowed from main for one update or draw.
+pub struct Context<'a> {
+    pub game: &'a mut Game,
+    pub settings: &'a mut Settings,
+    pub theme_manager: &'a mut ThemeManager,
+    pub audio_manager: &'a mut SoundManager,
+    pub camera_effects: &'a mut CameraEffects,
+    pub post_process: &'a mut PostProcess,
+    pub stats: &'a Stats,
+    /// the connection of a networked match, set by the lobby
+    pub net: &'a mut Option<NetSession>,
+}
+
+/// How the screen changes between two scenes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transition {
+    Cut,
+    /// fades to the background color and back
+    Fade,
+    /// a bar sweeps over the screen from left to right
+    Wipe,
+}
+
+/// What a scene wants the manager to do after its update.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SceneCommand {
+    None,
+    /// puts a scene on top, the scenes below stay on the stack
+    Push(GameState, Transition),
+    Pop(Transition),
+    /// swaps the top scene for another one
+    Replace(GameState, Transition),
+    /// clears the stack and starts over with a single scene
+    Reset(GameState, Transition),
+    Quit,
+}
+
+/// One screen of the game, each GameState has exactly one scene.
+pub trait Scene {
+    /// Called when the scene becomes part of the stack.
+    fn enter(&mut self, _ctx: &mut Context) {}
+    /// Called when the scene is removed from the stack.
+    fn exit(&mut self, _ctx: &mut Context) {}
+    /// Only the top scene is updated.
+    fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) -> SceneCommand;
+    fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context);
+    /// Overlays are drawn on top of the scene below them instead of replacing it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+// seconds a fade or wipe takes, half to cover the screen and half to reveal the next scene
+const TRANSITION_TIME: f32 = 0.5;
+
+struct ActiveTransition {
+    kind: Transition,
+    // the "cover" track goes from 0.0 to 1.0 and back
+    timeline: Timeline,
+    // applied once the screen is covered
+    pending: Option<SceneCommand>,
+}
+
+/// A stack of scenes, the top one gets the input and everything down to the first
+/// non-overlay scene is drawn.
+pub struct SceneManager {
+    scenes: HashMap<GameState, Box<dyn Scene>>,
+    stack: Vec<GameState>,
+    transition: Option<ActiveTransition>,
+    quit: bool,
+}
+
+impl SceneManager {
+    pub fn new() -> SceneManager {
+        SceneManager {
+            scenes: HashMap::new(),
+            stack: Vec::new(),
+            transition: None,
+            quit: false,
+        }
+    }
+
+    pub fn register(&mut self, state: GameState, scene: Box<dyn Scene>) {
+        self.scenes.insert(state, scene);
+    }
+
+    /// The top scene, MainMenu on an empty stack.
+    pub fn current(&self) -> GameState {
+        self.stack.last().copied().unwrap_or(GameState::MainMenu)
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    pub fn update(&mut self, rl: &mut RaylibHandle, ctx: &mut Context) {
+        // scenes don't get input while the screen changes
+        if self.transition.is_some() {
+            self.update_transition(ctx);
+            return;
+        }
+
+        let state = self.current();
+        let command = match self.scenes.get_mut(&state) {
+            Some(scene) => scene.update(rl, ctx),
+            None => SceneCommand::None,
+        };
+        self.run(command, ctx);
+    }
+
+    /// Runs `command`, after the screen is covered when it asks for a transition.
+    pub fn run(&mut self, command: SceneCommand, ctx: &mut Context) {
+        let kind = match command {
+            SceneCommand::None => return,
+            SceneCommand::Quit => {
+                self.quit = true;
+                return;
+            }
+            SceneCommand::Push(_, kind) | SceneCommand::Pop(kind) |
+            SceneCommand::Replace(_, kind) | SceneCommand::Reset(_, kind) => kind,
+        };
+
+        if kind == Transition::Cut {
+            self.apply(command, ctx);
+            return;
+        }
+
+        let half = TRANSITION_TIME / 2.0;
+        self.transition = Some(ActiveTransition {
+            kind,
+            timeline: Timeline::new()
+                .then("cover", 0.0, 1.0, half, Ease::InOutCubic)
+                .then("cover", 1.0, 0.0, half, Ease::InOutCubic),
+            pending: Some(command),
+        });
+    }
+
+    fn update_transition(&mut self, ctx: &mut Context) {
+        let command = match self.transition.as_mut() {
+            Some(transition) => {
+                transition.timeline.update(unsafe { DELTA_TIME });
+                if transition.timeline.time() >= TRANSITION_TIME / 2.0 { transition.pending.take() } else { None }
+            }
+            None => return,
+        };
+
+        if let Some(command) = command {
+            self.apply(command, ctx);
+        }
+
+        if self.transition.as_ref().map_or(false, |transition| transition.timeline.is_finished()) {
+            self.transition = None;
+        }
+    }
+
+    fn apply(&mut self, command: SceneCommand, ctx: &mut Context) {
+        match command {
+            SceneCommand::Push(state, _) => self.push(state, ctx),
+            SceneCommand::Pop(_) => self.pop(ctx),
+            SceneCommand::Replace(state, _) => {
+                self.pop(ctx);
+                self.push(state, ctx);
+            }
+            SceneCommand::Reset(state, _) => {
+                while !self.stack.is_empty() {
+                    self.pop(ctx);
+                }
+                self.push(state, ctx);
+            }
+            SceneCommand::None | SceneCommand::Quit => {}
+        }
+    }
+
+    fn push(&mut self, state: GameState, ctx: &mut Context) {
+        self.stack.push(state);
+        if let Some(scene) = self.scenes.get_mut(&state) {
+            scene.enter(ctx);
+        }
+    }
+
+    fn pop(&mut self, ctx: &mut Context) {
+        if let Some(state) = self.stack.pop() {
+            if let Some(scene) = self.scenes.get_mut(&state) {
+                scene.exit(ctx);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, d: &mut RaylibDrawHandle, ctx: &mut Context) {
+        // start at the topmost scene that isn't an overlay
+        let bottom = self.stack.iter()
+            .rposition(|state| self.scenes.get(state).map_or(false, |scene| !scene.is_overlay()))
+            .unwrap_or(0);
+
+        for state in self.stack[bottom..].iter() {
+            if let Some(scene) = self.scenes.get_mut(state) {
+                scene.draw(d, ctx);
+            }
+        }
+
+        if let Some(transition) = &self.transition {
+            draw_transition(d, transition, ctx.game.theme.colors.background);
+        }
+    }
+}
+
+fn draw_transition(d: &mut RaylibDrawHandle, transition: &ActiveTransition, color: Color) {
+    let width = d.get_screen_width() as f32;
+    let height = d.get_screen_height() as f32;
+    let cover = transition.timeline.value("cover");
+
+    match transition.kind {
+        Transition::Cut => {}
+        Transition::Fade => d.draw_rectangle_v(Vector2::zero(), Vector2::new(width, height), color.fade(cover)),
+        Transition::Wipe => {
+            // covers from the left edge, then uncovers towards the right edge
+            let x = if transition.pending.is_some() { 0.0 } else { width * (1.0 - cover) };
+            d.draw_rectangle_v(Vector2::new(x, 0.0), Vector2::new(width * cover, height), color);
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use serde::{Deserialize, Serialize};
+use crate::audiosystem::{SoundTheme, VoiceStealPolicy};
+use crate::gamestate::GameState;
+use crate::trail::TrailStyle;
+
+/// Settings file looked up in the working directory.
+pub const SETTINGS_PATH: &str = "settings.toml";
+/// Longest rollback input delay, together with the 16 predicted ticks it stays well under the
+/// 32 inputs a packet holds.
+pub const MAX_INPUT_DELAY: u32 = 8;
+
+/// Slowest instant replay playback, at zero or below the replay would never end.
+pub const MIN_REPLAY_SPEED: f32 = 0.1;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub audio: AudioSettings,
+    pub music: MusicSettings,
+    pub trail: TrailSettings,
+    pub serve: ServeSettings,
+    pub four_player: FourPlayerSettings,
+    pub multi_ball: MultiBallSettings,
+    pub replay: ReplaySettings,
+    pub instant_replay: InstantReplaySettings,
+    pub net: NetSettings,
+    pub particles: ParticleSettings,
+    pub effects: EffectSettings,
+    pub post_process: PostProcessSettings,
+    pub theme: ThemeSettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub sound_theme: SoundTheme,
+    /// How many instances of the same sound may play at once.
+    pub max_voices: usize,
+    pub voice_steal_policy: VoiceStealPolicy,
+    /// Pan sound effects towards where they happened in the arena.
+    pub positional: bool,
+    /// How much quieter a fully panned sound is, 0.0 disables attenuation.
+    pub distance_attenuation: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            sound_theme: SoundTheme::Samples,
+            max_voices: 4,
+            voice_steal_policy: VoiceStealPolicy::Oldest,
+            positional: true,
+            distance_attenuation: 0.0,
+        }
+    }
+}
+
+/// Playlists are lists of OGG, WAV, MP3, XM or MOD files, played in order.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MusicSettings {
+    pub volume: f32,
+    /// Seconds it takes to fade from one track to the next.
+    pub crossfade: f32,
+    /// Start the playlist over after the last track.
+    pub looping: bool,
+    /// Music volume multiplier while sound effects play.
+    pub duck_volume: f32,
+    /// Seconds the music stays ducked after a sound effect.
+    pub duck_time: f32,
+    pub menu: Vec<String>,
+    pub in_match: Vec<String>,
+    pub game_over: Vec<String>,
+}
+
+impl Default for MusicSettings {
+    fn default() -> Self {
+        Self {
+            volume: 0.5,
+            crossfade: 1.5,
+            looping: true,
+            duck_volume: 0.4,
+            duck_time: 0.25,
+            menu: vec![String::from("res/music/menu.ogg")],
+            in_match: vec![String::from("res/music/match.ogg")],
+            game_over: vec![String::from("res/music/game_over.ogg")],
+        }
+    }
+}
+
+impl MusicSettings {
+    pub fn playlist(&self, state: GameState) -> &Vec<String> {
+        match state {
+            GameState::Credits | GameState::MainMenu | GameState::Options | GameState::Lobby => &self.menu,
+            GameState::Playing | GameState::Paused | GameState::Replay | GameState::InstantReplay |
+            GameState::Online => &self.in_match,
+            GameState::GameOver => &self.game_over,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TrailSettings {
+    pub style: TrailStyle,
+    /// Number of simulation ticks the trail reaches back.
+    pub length: usize,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        Self {
+            style: TrailStyle::Circles,
+            length: 10,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ServeSettings {
+    /// Seconds counted down before each serve, 0 serves right away.
+    pub countdown: u32,
+    /// Draw the 3-2-1-GO numbers, the pause before the serve stays when hidden.
+    pub show_countdown: bool,
+}
+
+impl Default for ServeSettings {
+    fn default() -> Self {
+        Self {
+            countdown: 3,
+            show_countdown: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct FourPlayerSettings {
+    pub scoring: FourPlayerScoring,
+    /// Goals a side can let in before it is out, with lives scoring.
+    pub lives: i32,
+    /// Points needed to win, with points scoring.
+    pub points: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FourPlayerScoring {
+    /// every goal costs the side that let it in a life, the last side left wins
+    Lives,
+    /// the side that touched the ball last gets the point
+    Points,
+}
+
+impl Default for FourPlayerSettings {
+    fn default() -> Self {
+        Self {
+            scoring: FourPlayerScoring::Lives,
+            lives: 3,
+            points: 7,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MultiBallSettings {
+    pub spawn: MultiBallSpawn,
+    /// Seconds between new balls, with interval spawning.
+    pub interval: f32,
+    /// Paddle hits between new balls, with hits spawning.
+    pub hits: u32,
+    /// Balls in play at most, no more join the rally once there are this many.
+    pub max_balls: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MultiBallSpawn {
+    /// a new ball joins every few seconds of the rally
+    Interval,
+    /// a new ball joins after every few paddle hits
+    Hits,
+}
+
+impl Default for MultiBallSettings {
+    fn default() -> Self {
+        Self {
+            spawn: MultiBallSpawn::Interval,
+            interval: 8.0,
+            hits: 4,
+            max_balls: 4,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ReplaySettings {
+    /// Save every match to a replay file.
+    pub record: bool,
+    pub directory: String,
+}
+
+impl Default for ReplaySettings {
+    fn default() -> Self {
+        Self {
+            record: true,
+            directory: String::from("replays"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct InstantReplaySettings {
+    /// Show the end of the rally again after every goal.
+    pub enabled: bool,
+    /// Seconds before the goal that are shown.
+    pub seconds: f32,
+    /// Playback speed, below 1.0 is slow motion, at least 0.1.
+    pub speed: f32,
+}
+
+impl Default for InstantReplaySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            seconds: 3.0,
+            speed: 0.5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NetSettings {
+    /// UDP port a hosted game listens on.
+    pub port: u16,
+    /// Host the join screen connects to, remembered from the last join.
+    pub address: String,
+    /// Seconds without a packet before the other side counts as gone.
+    pub timeout: f32,
+    /// How a hosted match is synchronized, the player who joins follows the host.
+    pub netcode: Netcode,
+    /// Ticks local inputs are held back in rollback matches, fewer rollbacks for a little lag, at most 8.
+    pub input_delay: u32,
+    /// Name hosted games are listed under on the LAN.
+    pub name: String,
+    /// UDP port hosts announce their games on.
+    pub discovery_port: u16,
+    /// Most spectators a hosted match sends its state to.
+    pub max_spectators: usize,
+    /// Seconds a spectator shows the match behind the host, smooths over late packets.
+    pub spectator_delay: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Netcode {
+    /// the host simulates and the other player sees its results
+    Host,
+    /// both players simulate, predict the other's input and go back when they guessed wrong
+    Rollback,
+}
+
+impl Default for NetSettings {
+    fn default() -> Self {
+        Self {
+            port: 7750,
+            address: String::from("127.0.0.1:7750"),
+            timeout: 5.0,
+            netcode: Netcode::Rollback,
+            input_delay: 2,
+            name: String::from("pong50th"),
+            discovery_port: 7751,
+            max_spectators: 8,
+            spectator_delay: 0.5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ParticleSettings {
+    pub enabled: bool,
+    /// Size of the particle pool, the oldest particles are replaced when it is full.
+    pub max_particles: usize,
+    /// Most particles drawn in one frame.
+    pub budget: usize,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_particles: 2048,
+            budget: 1024,
+        }
+    }
+}
+
+/// Camera effects, each intensity goes from 0.0 (off) to 1.0.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct EffectSettings {
+    /// Turns every camera effect off.
+    pub enabled: bool,
+    pub shake: f32,
+    pub hit_stop: f32,
+    pub zoom: f32,
+    /// Slowest ball speed off the paddle that still triggers a hit-stop, between about 2100 for a flat
+    /// return and 4200 for a steep one off the paddle edge.
+    pub hit_stop_speed: f32,
+}
+
+impl Default for EffectSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            shake: 0.5,
+            hit_stop: 0.5,
+            zoom: 0.5,
+            hit_stop_speed: 3600.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PostProcessSettings {
+    pub enabled: bool,
+    /// Fragment shaders applied in order, loaded from `<shader_directory>/<name>.fs`.
+    pub passes: Vec<String>,
+    pub shader_directory: String,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            passes: ["bloom", "scanlines", "chromatic_aberration", "curvature", "vignette"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            shader_directory: String::from("res/shaders"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ThemeSettings {
+    /// Name of a built-in theme or of a theme file in the themes directory.
+    pub name: String,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            name: String::from("Classic 1972"),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads settings from `path`, missing files and fields fall back to the defaults.
+    pub fn load(path: &str) -> Settings {
+        match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map(Settings::clamped).unwrap_or_else(|err| {
+                eprintln!("failed to parse {}: {}", path, err);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    // pulls values the game can't work with back into range
+    fn clamped(mut self) -> Settings {
+        self.net.input_delay = self.net.input_delay.min(MAX_INPUT_DELAY);
+        // max also turns a NaN into the minimum
+        self.instant_replay.speed = self.instant_replay.speed.max(MIN_REPLAY_SPEED);
+        self
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let text = toml::to_string_pretty(self)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        std::fs::write(path, text)
+    }
+}
@@ -0,0 +1,60 @@
+use raylib::prelude::*;
+
+// segments lit per digit, bits are a b c d e f g
+// a is the top, then clockwise, g is the middle bar
+const DIGITS: [u8; 10] = [
+    0b1111110, // 0
+    0b0110000, // 1
+    0b1101101, // 2
+    0b1111001, // 3
+    0b0110011, // 4
+    0b1011011, // 5
+    0b1011111, // 6
+    0b1110000, // 7
+    0b1111111, // 8
+    0b1111011, // 9
+];
+
+// width of a digit and the space between digits relative to its height
+const DIGIT_WIDTH: f32 = 0.5;
+const DIGIT_SPACING: f32 = 0.25;
+const THICKNESS: f32 = 0.12;
+
+/// Width of `value` drawn with digits `height` pixels tall.
+pub fn measure(value: u32, height: f32) -> f32 {
+    let digits = value.to_string().len() as f32;
+    digits * height * DIGIT_WIDTH + (digits - 1.0) * height * DIGIT_SPACING
+}
+
+/// Draws `value` as blocky segments like the score of the 1972 cabinet, `position` is the top left corner.
+pub fn draw(d: &mut RaylibDrawHandle, value: u32, position: Vector2, height: f32, color: Color) {
+    let mut x = position.x;
+    for character in value.to_string().chars() {
+        let digit = character.to_digit(10).unwrap_or(0) as usize;
+        draw_digit(d, DIGITS[digit], Vector2::new(x, position.y), height, color);
+        x += height * (DIGIT_WIDTH + DIGIT_SPACING);
+    }
+}
+
+fn draw_digit(d: &mut RaylibDrawHandle, segments: u8, position: Vector2, height: f32, color: Color) {
+    let width = height * DIGIT_WIDTH;
+    let thickness = height * THICKNESS;
+    let half = (height - thickness) / 2.0;
+
+    // segments overlap at the corners so the digits stay solid blocks
+    let bars = [
+        (Vector2::new(0.0, 0.0), Vector2::new(width, thickness)),                       // a
+        (Vector2::new(width - thickness, 0.0), Vector2::new(thickness, half + thickness)), // b
+        (Vector2::new(width - thickness, half), Vector2::new(thickness, half + thickness)), // c
+        (Vector2::new(0.0, height - thickness), Vector2::new(width, thickness)),        // d
+        (Vector2::new(0.0, half), Vector2::new(thickness, half + thickness)),           // e
+        (Vector2::new(0.0, 0.0), Vector2::new(thickness, half + thickness)),            // f
+        (Vector2::new(0.0, half), Vector2::new(width, thickness)),                      // g
+    ];
+
+    for (i, (offset, size)) in bars.iter().enumerate() {
+        if segments & (1 << (6 - i)) != 0 {
+            d.draw_rectangle_v(position + *offset, *size, color);
+        }
+    }
+}
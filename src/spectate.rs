@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use crate::events::{emit, GameEvent};
+use crate::game::{Game, MatchState};
+use crate::net::{Connection, Message, PROTOCOL_VERSION};
+use crate::settings::NetSettings;
+use crate::time::{self, DELTA_TIME};
+
+// seconds between the requests a spectator repeats so the host keeps sending
+const WATCH_INTERVAL: f32 = 1.0;
+
+struct Watcher {
+    address: SocketAddr,
+    silence: f32,
+}
+
+/// The people watching a hosted match, they get every state the host has but can't play.
+pub struct Spectators {
+    welcome: Message,
+    watchers: Vec<Watcher>,
+}
+
+impl Spectators {
+    pub fn new(welcome: Message) -> Spectators {
+        Spectators { welcome, watchers: Vec::new() }
+    }
+
+    pub fn count(&self) -> usize {
+        self.watchers.len()
+    }
+
+    /// Handles a message from someone who doesn't play in the match.
+    pub fn receive(&mut self, connection: &Connection, message: Message, from: SocketAddr, settings: &NetSettings) {
+        match message {
+            Message::Watch { version } if version != PROTOCOL_VERSION => {
+                let reason = format!("host runs protocol version {}", PROTOCOL_VERSION);
+                connection.send(&Message::Refused { reason }, from);
+            }
+            Message::Watch { .. } => {
+                let count = self.watchers.len();
+                match self.watchers.iter_mut().find(|watcher| watcher.address == from) {
+                    Some(watcher) => watcher.silence = 0.0,
+                    None if count < settings.max_spectators => {
+                        self.watchers.push(Watcher { address: from, silence: 0.0 });
+                    }
+                    None => {
+                        connection.send(&Message::Refused { reason: String::from("no room for more spectators") }, from);
+                        return;
+                    }
+                }
+                // in case it got lost, the spectator ignores it once it watches
+                connection.send(&self.welcome, from);
+            }
+            Message::Join { .. } => {
+                connection.send(&Message::Refused { reason: String::from("game is full") }, from);
+            }
+            Message::Leave => self.watchers.retain(|watcher| watcher.address != from),
+            _ => {}
+        }
+    }
+
+    /// Sends the state after `tick` and the events of this frame to everyone still watching.
+    pub fn update(&mut self, connection: &Connection, tick: u32, state: MatchState, events: Vec<GameEvent>, settings: &NetSettings) {
+        let delta_time = unsafe { DELTA_TIME };
+        for watcher in self.watchers.iter_mut() {
+            watcher.silence += delta_time;
+        }
+        self.watchers.retain(|watcher| watcher.silence <= settings.timeout);
+        if self.watchers.is_empty() {
+            return;
+        }
+
+        let message = Message::State { tick, ack: 0, state, events };
+        for watcher in self.watchers.iter() {
+            connection.send(&message, watcher.address);
+        }
+    }
+
+    pub fn leave(&self, connection: &Connection) {
+        for watcher in self.watchers.iter() {
+            connection.send(&Message::Leave, watcher.address);
+        }
+    }
+}
+
+/// Follows a match somebody else hosts, everything is shown `spectator_delay` seconds late
+/// so a late packet doesn't make the ball stutter.
+pub struct SpectatorSession {
+    connection: Connection,
+    host: SocketAddr,
+    // host states with their events and the local time they arrived, oldest first
+    states: VecDeque<(f64, MatchState, Vec<GameEvent>)>,
+    // the state that was shown last, blended from toward the next one
+    shown: Option<(f64, MatchState)>,
+    latest_tick: u32,
+    watch_timer: f32,
+    silence: f32,
+}
+
+impl SpectatorSession {
+    pub fn new(connection: Connection, host: SocketAddr) -> SpectatorSession {
+        SpectatorSession {
+            connection,
+            host,
+            states: VecDeque::new(),
+            shown: None,
+            latest_tick: 0,
+            watch_timer: 0.0,
+            silence: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, game: &mut Game, settings: &NetSettings) -> Result<(), String> {
+        let now = unsafe { time::TIME };
+        let delta_time = unsafe { DELTA_TIME };
+        self.silence += delta_time;
+        while let Some((message, from)) = self.connection.receive() {
+            if from != self.host {
+                continue;
+            }
+            self.silence = 0.0;
+
+            match message {
+                Message::State { tick, state, events, .. } => {
+                    if tick <= self.latest_tick && self.latest_tick > 0 {
+                        continue;
+                    }
+                    self.latest_tick = tick;
+                    self.states.push_back((now, state, events));
+                }
+                Message::Leave => return Err(String::from("the host left")),
+                Message::Refused { reason } => return Err(reason),
+                _ => {}
+            }
+        }
+        if self.silence > settings.timeout {
+            return Err(String::from("connection lost"));
+        }
+
+        self.watch_timer -= delta_time;
+        if self.watch_timer <= 0.0 {
+            self.watch_timer = WATCH_INTERVAL;
+            self.connection.send(&Message::Watch { version: PROTOCOL_VERSION }, self.host);
+        }
+
+        let render_time = now - settings.spectator_delay as f64;
+        // states whose time came are shown, their sounds and effects play with them
+        while self.states.front().map_or(false, |(arrived, _, _)| *arrived <= render_time) {
+            if let Some((arrived, state, events)) = self.states.pop_front() {
+                for event in events {
+                    emit(event);
+                }
+                self.shown = Some((arrived, state));
+            }
+        }
+
+        let (from_time, from) = match self.shown {
+            Some(shown) => shown,
+            None => return Ok(()),
+        };
+        let mut state = from;
+        if let Some((to_time, to, _)) = self.states.front() {
+            let amount = ((render_time - from_time) / (to_time - from_time).max(1e-6)).min(1.0) as f32;
+            // don't sweep the ball across the arena when it was put back in the center
+            if from.ball_position.distance_to(to.ball_position) < game.screen_width as f32 / 4.0 {
+                state.ball_position = from.ball_position.lerp(to.ball_position, amount);
+            }
+            state.left_paddle = from.left_paddle + (to.left_paddle - from.left_paddle) * amount;
+            state.right_paddle = from.right_paddle + (to.right_paddle - from.right_paddle) * amount;
+        }
+        game.apply_match_state(&state);
+        Ok(())
+    }
+
+    pub fn leave(&self) {
+        self.connection.send(&Message::Leave, self.host);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::events::{EventSubscriber, GameEvent};
+
+/// Match statistics collected from gameplay events.
+#[derive(Default)]
+pub struct Stats {
+    /// by `Side::index`
+    pub hits: [u32; 4],
+    pub wall_bounces: u32,
+    pub rally: u32,
+    pub longest_rally: u32,
+    pub fastest_hit: f32,
+    pub matches_won: [u32; 4],
+    // set when a match is won, the next serve starts counting a new match
+    match_over: bool,
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    fn reset_match(&mut self) {
+        self.hits = [0; 4];
+        self.wall_bounces = 0;
+        self.rally = 0;
+        self.longest_rally = 0;
+        self.fastest_hit = 0.0;
+        self.match_over = false;
+    }
+}
+
+impl EventSubscriber for Stats {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::Serve { .. } => {
+                if self.match_over {
+                    self.reset_match();
+                }
+            }
+            GameEvent::PaddleHit { side, speed, .. } => {
+                self.hits[side.index()] += 1;
+                self.rally += 1;
+                self.longest_rally = self.longest_rally.max(self.rally);
+                self.fastest_hit = self.fastest_hit.max(speed);
+            }
+            GameEvent::WallBounce { .. } => self.wall_bounces += 1,
+            GameEvent::Countdown { .. } => {}
+            GameEvent::Scored { .. } => self.rally = 0,
+            GameEvent::MatchWon { side, .. } => {
+                self.matches_won[side.index()] += 1;
+                self.match_over = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use raylib::ffi::{Wave, WaveCopy};
+use crate::audiosystem::SoundType;
+
+const SAMPLE_RATE: u32 = 44100;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Oscillator {
+    /// duty is the part of the period spent high, 0.5 is a plain square
+    Square { duty: f32 },
+    Triangle,
+    /// pitched noise, a new random level is picked every period
+    Noise,
+}
+
+/// Attack, decay and release in seconds, sustain as a level between 0 and 1.
+#[derive(Clone, Copy, Debug)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Patch {
+    pub oscillator: Oscillator,
+    /// frequency at the start of the note
+    pub frequency: f32,
+    /// frequency at the end of the note, same as `frequency` for a steady tone
+    pub frequency_end: f32,
+    /// seconds the note is held before the release starts
+    pub duration: f32,
+    pub envelope: Envelope,
+    pub volume: f32,
+}
+
+impl Envelope {
+    pub const BLIP: Envelope = Envelope { attack: 0.002, decay: 0.0, sustain: 1.0, release: 0.01 };
+
+    fn level(&self, t: f32, duration: f32) -> f32 {
+        if t < self.attack {
+            t / self.attack
+        } else if t < self.attack + self.decay {
+            1.0 - (1.0 - self.sustain) * (t - self.attack) / self.decay
+        } else if t < duration {
+            self.sustain
+        } else if self.release > 0.0 {
+            (self.sustain * (1.0 - (t - duration) / self.release)).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Patch {
+    fn length(&self) -> f32 {
+        self.duration + self.envelope.release
+    }
+
+    /// Adds this patch on top of `buffer`, growing it when the patch is longer.
+    fn render_into(&self, buffer: &mut Vec<f32>) {
+        let length = self.length();
+        let sample_count = (length * SAMPLE_RATE as f32) as usize;
+        if buffer.len() < sample_count {
+            buffer.resize(sample_count, 0.0);
+        }
+
+        let mut phase = 0.0f32;
+        // 15 bit linear feedback shift register like the noise channel of old sound chips
+        let mut lfsr: u16 = 0x7fff;
+        let mut noise = 1.0f32;
+
+        for (i, sample) in buffer.iter_mut().take(sample_count).enumerate() {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let frequency = self.frequency + (self.frequency_end - self.frequency) * (t / length);
+
+            let value = match self.oscillator {
+                Oscillator::Square { duty } => if phase < duty { 1.0 } else { -1.0 },
+                Oscillator::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+                Oscillator::Noise => noise,
+            };
+            *sample += value * self.envelope.level(t, self.duration) * self.volume;
+
+            phase += frequency / SAMPLE_RATE as f32;
+            if phase >= 1.0 {
+                phase -= 1.0;
+                let bit = (lfsr ^ (lfsr >> 1)) & 1;
+                lfsr = (lfsr >> 1) | (bit << 14);
+                noise = if lfsr & 1 == 0 { 1.0 } else { -1.0 };
+            }
+        }
+    }
+}
+
+/// Mixes the patches into a 16 bit mono wave owned by raylib.
+pub fn render(patches: &[Patch]) -> Wave {
+    let mut buffer = Vec::new();
+    for patch in patches {
+        patch.render_into(&mut buffer);
+    }
+
+    let mut samples: Vec<i16> = buffer.iter()
+        .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect();
+
+    let wave = Wave {
+        sampleCount: samples.len() as u32,
+        sampleRate: SAMPLE_RATE,
+        sampleSize: 16,
+        channels: 1,
+        data: samples.as_mut_ptr() as *mut _,
+    };
+    // copy into memory allocated by raylib so UnloadWave can free it
+    unsafe { WaveCopy(wave) }
+}
+
+fn tone(oscillator: Oscillator, frequency: f32, frequency_end: f32, duration: f32, volume: f32) -> Patch {
+    Patch {
+        oscillator,
+        frequency,
+        frequency_end,
+        duration,
+        envelope: Envelope::BLIP,
+        volume,
+    }
+}
+
+/// Sounds in the style of the 1972 cabinet, which only had a handful of square wave beeps.
+pub fn retro_theme() -> HashMap<SoundType, Wave> {
+    let square = Oscillator::Square { duty: 0.5 };
+
+    let mut sound_map = HashMap::new();
+    sound_map.insert(SoundType::Bounce, render(&[
+        tone(square, 490.0, 490.0, 0.05, 0.6),
+    ]));
+    sound_map.insert(SoundType::WallBounce, render(&[
+        tone(square, 245.0, 245.0, 0.03, 0.6),
+    ]));
+    sound_map.insert(SoundType::PlayerScored, render(&[
+        tone(square, 490.0, 980.0, 0.25, 0.5),
+        tone(Oscillator::Triangle, 980.0, 1960.0, 0.25, 0.3),
+    ]));
+    sound_map.insert(SoundType::EnemyScored, render(&[
+        Patch {
+            envelope: Envelope { attack: 0.002, decay: 0.2, sustain: 0.4, release: 0.1 },
+            ..tone(square, 490.0, 196.0, 0.3, 0.5)
+        },
+        tone(Oscillator::Noise, 2000.0, 500.0, 0.1, 0.2),
+    ]));
+    sound_map.insert(SoundType::Countdown, render(&[
+        tone(square, 392.0, 392.0, 0.08, 0.5),
+    ]));
+    sound_map
+}
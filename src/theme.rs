@@ -0,0 +1,370 @@
+use raylib::ffi::{DrawTexturePro, FileExists, Font, LoadTexture, Texture2D, UnloadTexture};
+use raylib::prelude::*;
+use serde::Deserialize;
+use crate::colorhelper;
+use crate::const_c;
+use crate::fontmanager::{self, FontManager};
+use crate::sevensegment;
+
+// themes shipped with the game, more are loaded from THEME_DIRECTORY
+const BUILTIN_THEMES: [&str; 3] = [
+    include_str!("../res/themes/classic_1972.toml"),
+    include_str!("../res/themes/neon.toml"),
+    include_str!("../res/themes/high_contrast.toml"),
+];
+const THEME_DIRECTORY: &str = "themes";
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct ThemeColors {
+    #[serde(with = "colorhelper::hex")]
+    pub background: Color,
+    #[serde(with = "colorhelper::hex")]
+    pub ball: Color,
+    #[serde(with = "colorhelper::hex")]
+    pub left_paddle: Color,
+    #[serde(with = "colorhelper::hex")]
+    pub right_paddle: Color,
+    #[serde(with = "colorhelper::hex")]
+    pub center_line: Color,
+    #[serde(with = "colorhelper::hex")]
+    pub score: Color,
+    /// menus and other text
+    #[serde(with = "colorhelper::hex")]
+    pub text: Color,
+    /// highlighted menu entries
+    #[serde(with = "colorhelper::hex")]
+    pub accent: Color,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    Circle,
+    Square,
+    Rectangle,
+    Rounded,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ShapeStyle {
+    pub shape: Shape,
+    /// image drawn instead of the shape
+    #[serde(default)]
+    pub sprite: Option<String>,
+    /// drawn size relative to the collision size
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LineStyle {
+    Dashed,
+    Dotted,
+    Solid,
+    None,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct CenterLine {
+    pub style: LineStyle,
+    pub width: f32,
+    #[serde(default)]
+    pub dash: f32,
+    #[serde(default)]
+    pub gap: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreStyle {
+    /// blocky segment digits like the 1972 cabinet
+    SevenSegment,
+    /// the theme font, raylib's default font without one
+    Text,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct ScoreDisplay {
+    pub style: ScoreStyle,
+    /// digit height in pixels
+    pub size: f32,
+    /// distance of each score from the center line
+    pub margin: f32,
+    pub top: f32,
+}
+
+impl Default for ScoreDisplay {
+    fn default() -> Self {
+        ScoreDisplay {
+            style: ScoreStyle::Text,
+            size: 100.0,
+            margin: 50.0,
+            top: 50.0,
+        }
+    }
+}
+
+/// Textures and fonts of the selected theme, loaded by ThemeManager::select.
+#[derive(Clone, Copy, Default)]
+struct ThemeAssets {
+    ball: Option<Texture2D>,
+    paddle: Option<Texture2D>,
+    background: Option<Texture2D>,
+    font: Option<Font>,
+}
+
+/// How the arena looks, loaded from a toml file.
+#[derive(Deserialize, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub colors: ThemeColors,
+    pub ball: ShapeStyle,
+    pub paddles: ShapeStyle,
+    pub center_line: CenterLine,
+    #[serde(default)]
+    pub score: ScoreDisplay,
+    /// ttf, otf or bitmap font used for the score
+    #[serde(default)]
+    pub font: Option<String>,
+    #[serde(default)]
+    pub background_image: Option<String>,
+    #[serde(skip)]
+    assets: ThemeAssets,
+}
+
+impl Theme {
+    pub fn font(&self) -> Option<Font> {
+        self.assets.font
+    }
+
+    fn load_assets(&mut self, fonts: &mut FontManager) {
+        self.assets = ThemeAssets {
+            ball: self.ball.sprite.as_deref().and_then(load_texture),
+            paddle: self.paddles.sprite.as_deref().and_then(load_texture),
+            background: self.background_image.as_deref().and_then(load_texture),
+            font: self.font.as_deref().and_then(|path| fonts.load(path)),
+        };
+    }
+
+    // fonts stay cached in the FontManager
+    fn unload_assets(&mut self) {
+        unsafe {
+            for texture in [self.assets.ball, self.assets.paddle, self.assets.background].iter().flatten() {
+                UnloadTexture(*texture);
+            }
+        }
+        self.assets = ThemeAssets::default();
+    }
+
+    pub fn draw_background(&self, d: &mut RaylibDrawHandle, screen_width: i32, screen_height: i32) {
+        d.clear_background(self.colors.background);
+        if let Some(texture) = self.assets.background {
+            draw_sprite(texture, Vector2::zero(), Vector2::new(screen_width as f32, screen_height as f32), Color::WHITE);
+        }
+    }
+
+    pub fn draw_center_line(&self, d: &mut RaylibDrawHandle, screen_width: i32, screen_height: i32) {
+        let line = &self.center_line;
+        let x = screen_width as f32 / 2.0;
+        let color = self.colors.center_line;
+
+        match line.style {
+            LineStyle::None => {}
+            LineStyle::Solid => {
+                d.draw_rectangle_v(Vector2::new(x, 0.0), Vector2::new(line.width, screen_height as f32), color);
+            }
+            LineStyle::Dashed => {
+                let mut y = 0.0;
+                while y < screen_height as f32 {
+                    d.draw_rectangle_v(Vector2::new(x, y), Vector2::new(line.width, line.dash), color);
+                    y += line.dash + line.gap;
+                }
+            }
+            LineStyle::Dotted => {
+                let mut y = line.dash / 2.0;
+                while y < screen_height as f32 {
+                    d.draw_circle_v(Vector2::new(x + line.width / 2.0, y), line.width / 2.0, color);
+                    y += line.dash + line.gap;
+                }
+            }
+        }
+    }
+
+    /// Draws both scores with the left one right aligned and the right one left aligned around the center line.
+    pub fn draw_score(&self, d: &mut RaylibDrawHandle, score: (i32, i32), screen_width: i32) {
+        let display = &self.score;
+        let color = self.colors.score;
+        let center = screen_width as f32 / 2.0;
+        let (left, right) = (score.0.max(0) as u32, score.1.max(0) as u32);
+
+        match display.style {
+            ScoreStyle::SevenSegment => {
+                let left_width = sevensegment::measure(left, display.size);
+                sevensegment::draw(d, left, Vector2::new(center - display.margin - left_width, display.top), display.size, color);
+                sevensegment::draw(d, right, Vector2::new(center + display.margin, display.top), display.size, color);
+            }
+            ScoreStyle::Text => {
+                let font = self.font();
+                let left = left.to_string();
+                let left_width = fontmanager::measure(font, &left, display.size).x;
+                fontmanager::draw(font, &left, Vector2::new(center - display.margin - left_width, display.top), display.size, color);
+                fontmanager::draw(font, &right.to_string(), Vector2::new(center + display.margin, display.top), display.size, color);
+            }
+        }
+    }
+
+    /// Draws one side's score centered on `center`, for four player matches.
+    pub fn draw_side_score(&self, d: &mut RaylibDrawHandle, score: i32, center: Vector2) {
+        let display = &self.score;
+        let color = self.colors.score;
+        let score = score.max(0) as u32;
+
+        match display.style {
+            ScoreStyle::SevenSegment => {
+                let width = sevensegment::measure(score, display.size);
+                sevensegment::draw(d, score, center - Vector2::new(width, display.size) / 2.0, display.size, color);
+            }
+            ScoreStyle::Text => fontmanager::draw_centered(self.font(), &score.to_string(), center, display.size, color),
+        }
+    }
+
+    pub fn draw_ball(&self, d: &mut RaylibDrawHandle, position: Vector2, radius: f32) {
+        let radius = radius * self.ball.scale;
+        let color = self.colors.ball;
+
+        if let Some(texture) = self.assets.ball {
+            let size = Vector2::new(radius * 2.0, radius * 2.0);
+            draw_sprite(texture, position - size / 2.0, size, color);
+            return;
+        }
+
+        match self.ball.shape {
+            Shape::Square | Shape::Rectangle => {
+                d.draw_rectangle_v(position - Vector2::new(radius, radius), Vector2::new(radius * 2.0, radius * 2.0), color);
+            }
+            Shape::Circle | Shape::Rounded => d.draw_circle_v(position, radius, color),
+        }
+    }
+
+    pub fn draw_paddle(&self, d: &mut RaylibDrawHandle, position: Vector2, size: Vector2, color: Color) {
+        let scaled = size * self.paddles.scale;
+        let position = position - (scaled - size) / 2.0;
+
+        if let Some(texture) = self.assets.paddle {
+            draw_sprite(texture, position, scaled, color);
+            return;
+        }
+
+        match self.paddles.shape {
+            Shape::Rounded | Shape::Circle if scaled.x > scaled.y => {
+                // the flat paddles of a four player match have their caps left and right
+                let radius = scaled.y / 2.0;
+                d.draw_rectangle_v(position + Vector2::new(radius, 0.0), scaled - Vector2::new(radius * 2.0, 0.0), color);
+                d.draw_circle_v(position + Vector2::new(radius, radius), radius, color);
+                d.draw_circle_v(position + Vector2::new(scaled.x - radius, radius), radius, color);
+            }
+            Shape::Rounded | Shape::Circle => {
+                // a rectangle with half circle caps
+                let radius = scaled.x / 2.0;
+                d.draw_rectangle_v(position + Vector2::new(0.0, radius), scaled - Vector2::new(0.0, radius * 2.0), color);
+                d.draw_circle_v(position + Vector2::new(radius, radius), radius, color);
+                d.draw_circle_v(position + Vector2::new(radius, scaled.y - radius), radius, color);
+            }
+            Shape::Rectangle | Shape::Square => d.draw_rectangle_v(position, scaled, color),
+        }
+    }
+}
+
+fn load_texture(path: &str) -> Option<Texture2D> {
+    unsafe {
+        if !FileExists(const_c!(path)) {
+            eprintln!("theme image {} not found", path);
+            return None;
+        }
+        Some(LoadTexture(const_c!(path)))
+    }
+}
+
+fn draw_sprite(texture: Texture2D, position: Vector2, size: Vector2, tint: Color) {
+    unsafe {
+        DrawTexturePro(texture,
+                       ffi::Rectangle { x: 0.0, y: 0.0, width: texture.width as f32, height: texture.height as f32 },
+                       ffi::Rectangle { x: position.x, y: position.y, width: size.x, height: size.y },
+                       Vector2::zero().into(),
+                       0.0,
+                       tint.into());
+    }
+}
+
+/// Every available theme, only the selected one has its assets loaded.
+pub struct ThemeManager {
+    themes: Vec<Theme>,
+    selected: usize,
+    fonts: FontManager,
+}
+
+impl ThemeManager {
+    pub fn new(name: &str) -> ThemeManager {
+        let mut themes: Vec<Theme> = BUILTIN_THEMES.iter()
+            .map(|text| toml::from_str(text).expect("built-in theme is invalid"))
+            .collect();
+        themes.extend(load_theme_directory(THEME_DIRECTORY));
+
+        let mut manager = ThemeManager {
+            themes,
+            selected: 0,
+            fonts: FontManager::new(),
+        };
+        manager.select(name);
+        manager
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.themes.iter().map(|theme| theme.name.clone()).collect()
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.selected]
+    }
+
+    /// Selects the theme called `name`, the first theme if there is none.
+    pub fn select(&mut self, name: &str) -> &Theme {
+        let index = self.themes.iter().position(|theme| theme.name == name).unwrap_or(0);
+        self.themes[self.selected].unload_assets();
+        self.selected = index;
+        self.themes[index].load_assets(&mut self.fonts);
+        &self.themes[index]
+    }
+}
+
+fn load_theme_directory(path: &str) -> Vec<Theme> {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |extension| extension == "toml"))
+        .filter_map(|path| {
+            let text = std::fs::read_to_string(&path).ok()?;
+            let mut theme: Theme = toml::from_str(&text)
+                .map_err(|err| eprintln!("failed to load theme {}: {}", path.display(), err))
+                .ok()?;
+            // dashes and dots repeat every dash + gap pixels, less would never reach the bottom
+            let line = &mut theme.center_line;
+            let step = line.dash + line.gap;
+            if matches!(line.style, LineStyle::Dashed | LineStyle::Dotted) && (step.is_nan() || step < 1.0) {
+                eprintln!("theme {} needs a dash and gap of at least 1 pixel together, its center line is solid", path.display());
+                line.style = LineStyle::Solid;
+            }
+            Some(theme)
+        })
+        .collect()
+}
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use raylib::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::settings::TrailSettings;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailStyle {
+    Off,
+    /// shrinking circles that fade out
+    Circles,
+    /// a tapering band through the previous positions
+    Ribbon,
+    /// full size ball images decaying like the phosphor of an old CRT
+    Phosphor,
+}
+
+// alpha kept per tick by the phosphor style
+const PHOSPHOR_DECAY: f32 = 0.8;
+
+/// Draws the trail behind the ball, `history` starts with the newest position.
+pub fn draw_trail(d: &mut RaylibDrawHandle, history: &VecDeque<Vector2>, radius: f32, color: Color, settings: &TrailSettings) {
+    let length = history.len().min(settings.length);
+    if length < 2 {
+        return;
+    }
+
+    match settings.style {
+        TrailStyle::Off => {}
+        TrailStyle::Circles => {
+            for (i, position) in history.iter().take(length).enumerate().skip(1).rev() {
+                let t = i as f32 / length as f32;
+                d.draw_circle_v(*position, radius * (1.0 - t * 0.7), color.fade((1.0 - t) * 0.6));
+            }
+        }
+        TrailStyle::Ribbon => {
+            for i in (1..length).rev() {
+                let t = i as f32 / length as f32;
+                let thickness = radius * 2.0 * (1.0 - t);
+                let faded = color.fade((1.0 - t) * 0.8);
+                d.draw_line_ex(history[i], history[i - 1], thickness, faded);
+                // round the joints between segments
+                d.draw_circle_v(history[i - 1], thickness / 2.0, faded);
+            }
+        }
+        TrailStyle::Phosphor => {
+            let mut alpha = PHOSPHOR_DECAY;
+            for position in history.iter().take(length).skip(1) {
+                d.draw_circle_v(*position, radius, color.fade(alpha * 0.5));
+                alpha *= PHOSPHOR_DECAY;
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+/// Easing curves, `apply` maps progress 0.0..1.0 to eased progress.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Ease {
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    /// overshoots the target and settles back
+    OutBack,
+    OutElastic,
+}
+
+impl Ease {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::InQuad => t * t,
+            Ease::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Ease::InOutQuad => if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 },
+            Ease::InCubic => t * t * t,
+            Ease::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Ease::InOutCubic => if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 },
+            Ease::OutBack => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.0;
+                1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
+            }
+            Ease::OutElastic => {
+                if t == 0.0 || t == 1.0 {
+                    return t;
+                }
+                let c4 = 2.0 * std::f32::consts::PI / 3.0;
+                2.0_f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * c4).sin() + 1.0
+            }
+        }
+    }
+}
+
+/// Value between `from` and `to` at progress `t`, 0.0 to 1.0.
+pub fn tween(from: f32, to: f32, t: f32, ease: Ease) -> f32 {
+    from + (to - from) * ease.apply(t)
+}
+
+// one tween of a track, `start` is relative to the start of the timeline
+#[derive(Clone, Debug)]
+struct Key {
+    track: &'static str,
+    from: f32,
+    to: f32,
+    start: f32,
+    duration: f32,
+    ease: Ease,
+}
+
+impl Key {
+    fn value(&self, time: f32) -> f32 {
+        let t = if self.duration > 0.0 { (time - self.start) / self.duration } else { 1.0 };
+        tween(self.from, self.to, t, self.ease)
+    }
+}
+
+/// Named tracks of tweens laid out on a shared clock.
+///
+/// `then` sequences a tween after the previous one, `with` runs it alongside the previous one
+/// and `at` places it at a start time relative to the beginning of the timeline.
+#[derive(Clone, Debug, Default)]
+pub struct Timeline {
+    keys: Vec<Key>,
+    time: f32,
+    // where the next `then` starts
+    cursor: f32,
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline::default()
+    }
+
+    pub fn then(mut self, track: &'static str, from: f32, to: f32, duration: f32, ease: Ease) -> Timeline {
+        let start = self.cursor;
+        self.cursor += duration;
+        self.at(start, track, from, to, duration, ease)
+    }
+
+    pub fn with(self, track: &'static str, from: f32, to: f32, duration: f32, ease: Ease) -> Timeline {
+        let start = self.keys.last().map_or(0.0, |key| key.start);
+        self.at(start, track, from, to, duration, ease)
+    }
+
+    pub fn at(mut self, start: f32, track: &'static str, from: f32, to: f32, duration: f32, ease: Ease) -> Timeline {
+        self.keys.push(Key { track, from, to, start, duration, ease });
+        self.cursor = self.cursor.max(start + duration);
+        self
+    }
+
+    /// Delays the next `then` by `seconds`.
+    pub fn wait(mut self, seconds: f32) -> Timeline {
+        self.cursor += seconds;
+        self
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// End of the last tween or wait.
+    pub fn duration(&self) -> f32 {
+        self.cursor
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.duration()
+    }
+
+    pub fn value(&self, track: &str) -> f32 {
+        self.sample(track, self.time)
+    }
+
+    /// Value of `track` at `time`, the latest tween that started wins.
+    /// Before its first tween a track holds that tween's `from`, 0.0 if the track doesn't exist.
+    pub fn sample(&self, track: &str, time: f32) -> f32 {
+        let keys = self.keys.iter().filter(|key| key.track == track);
+        let started = keys.clone()
+            .filter(|key| key.start <= time)
+            .max_by(|a, b| a.start.total_cmp(&b.start));
+        let current = started.or_else(|| keys.min_by(|a, b| a.start.total_cmp(&b.start)));
+        current.map_or(0.0, |key| key.value(time))
+    }
+}